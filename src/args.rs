@@ -0,0 +1,237 @@
+use proc_macro2::Span;
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Ident, Lifetime, LitStr, Path, PredicateType, Token, Type, TypeParamBound,
+    WherePredicate,
+};
+
+mod kw {
+    syn::custom_keyword!(local);
+    syn::custom_keyword!(box_future_type);
+    syn::custom_keyword!(no_std);
+    syn::custom_keyword!(debug);
+    syn::custom_keyword!(lifetime);
+    syn::custom_keyword!(impl_future);
+    syn::custom_keyword!(bound);
+    syn::custom_keyword!(export_futures);
+    syn::custom_keyword!(future_send_bound);
+    syn::custom_keyword!(lint_trivial_async);
+    syn::custom_keyword!(compat);
+}
+
+#[derive(Clone, Default)]
+pub struct Args {
+    pub local: bool,
+    pub box_future_type: Option<Path>,
+    pub no_std: bool,
+    pub debug: bool,
+    pub lifetime: Option<Lifetime>,
+    pub impl_future: bool,
+    pub bounds: Vec<WherePredicate>,
+    pub export_futures: bool,
+    pub future_send_bound: Option<Bounds>,
+    pub lint_trivial_async: bool,
+    pub compat: bool,
+}
+
+pub type Bounds = Punctuated<TypeParamBound, Token![+]>;
+
+impl Args {
+    pub fn future_lifetime(&self) -> Lifetime {
+        match &self.lifetime {
+            Some(lifetime) => lifetime.clone(),
+            None => Lifetime::new("'async_trait", Span::call_site()),
+        }
+    }
+
+    // What the returned future has in place of Send, unless it is local.
+    pub fn send_bounds(&self) -> Bounds {
+        match &self.future_send_bound {
+            Some(bounds) => bounds.clone(),
+            None => parse_quote!(::core::marker::Send),
+        }
+    }
+
+    // Which of Send and Sync the captures of the future need for it to meet
+    // `send_bounds`. A custom marker trait is assumed to stand in for Send.
+    pub fn future_markers(&self) -> Vec<&'static str> {
+        let bounds = match &self.future_send_bound {
+            Some(bounds) => bounds,
+            None => return vec!["Send"],
+        };
+        let mut markers = Vec::new();
+        for bound in bounds {
+            let marker = match bound {
+                TypeParamBound::Trait(_) => auto_trait(bound).unwrap_or("Send"),
+                TypeParamBound::Lifetime(_) => continue,
+            };
+            if !markers.contains(&marker) {
+                markers.push(marker);
+            }
+        }
+        markers
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args = Args::default();
+        let mut seen = Vec::new();
+
+        while !input.is_empty() {
+            // Only `bound` is meant to be repeated, anything else would
+            // silently override the earlier occurrence.
+            if let Ok(name) = input.fork().parse::<Ident>() {
+                if name != "bound" {
+                    if seen.contains(&name) {
+                        return Err(Error::new(
+                            name.span(),
+                            format!("duplicate {} argument", name),
+                        ));
+                    }
+                    seen.push(name);
+                }
+            }
+            let lookahead = input.lookahead1();
+            if lookahead.peek(kw::local) {
+                input.parse::<kw::local>()?;
+                args.local = true;
+            } else if lookahead.peek(kw::box_future_type) {
+                input.parse::<kw::box_future_type>()?;
+                input.parse::<Token![=]>()?;
+                args.box_future_type = Some(parse_lit(input, "a path to a type alias")?);
+            } else if lookahead.peek(kw::no_std) {
+                input.parse::<kw::no_std>()?;
+                args.no_std = true;
+            } else if lookahead.peek(kw::debug) {
+                input.parse::<kw::debug>()?;
+                args.debug = true;
+            } else if lookahead.peek(kw::lifetime) {
+                input.parse::<kw::lifetime>()?;
+                input.parse::<Token![=]>()?;
+                args.lifetime = Some(parse_lit(input, "a lifetime")?);
+            } else if lookahead.peek(kw::impl_future) {
+                input.parse::<kw::impl_future>()?;
+                args.impl_future = true;
+            } else if lookahead.peek(kw::bound) {
+                input.parse::<kw::bound>()?;
+                input.parse::<Token![=]>()?;
+                args.bounds
+                    .push(parse_lit(input, "a where-clause predicate")?);
+            } else if lookahead.peek(kw::future_send_bound) {
+                input.parse::<kw::future_send_bound>()?;
+                input.parse::<Token![=]>()?;
+                args.future_send_bound = Some(parse_future_bound(input)?);
+            } else if lookahead.peek(kw::export_futures) {
+                input.parse::<kw::export_futures>()?;
+                args.export_futures = true;
+            } else if lookahead.peek(kw::lint_trivial_async) {
+                input.parse::<kw::lint_trivial_async>()?;
+                args.lint_trivial_async = true;
+            } else if lookahead.peek(kw::compat) {
+                input.parse::<kw::compat>()?;
+                args.compat = true;
+            } else {
+                return Err(lookahead.error());
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        let exclusive = [
+            ("impl_future", args.impl_future),
+            ("box_future_type", args.box_future_type.is_some()),
+            ("export_futures", args.export_futures),
+        ];
+        let given: Vec<&str> = exclusive
+            .iter()
+            .filter(|(_, given)| *given)
+            .map(|(name, _)| *name)
+            .collect();
+        if given.len() > 1 {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("{} cannot be used together", given.join(" and ")),
+            ));
+        }
+        // A `dyn Future` can only carry auto traits on top of its lifetime.
+        if let Some(bounds) = &args.future_send_bound {
+            if !args.impl_future {
+                if let Some(bound) = bounds.iter().find(|bound| auto_trait(bound).is_none()) {
+                    return Err(Error::new_spanned(
+                        bound,
+                        "future_send_bound only supports Send and Sync on boxed futures, \
+                         other bounds need impl_future",
+                    ));
+                }
+            }
+        }
+        // The alias decides the bounds of the future on its own.
+        if args.box_future_type.is_some() && args.future_send_bound.is_some() {
+            return Err(Error::new(
+                Span::call_site(),
+                "box_future_type and future_send_bound cannot be used together",
+            ));
+        }
+        // The alias would only exist in one of the two versions of the trait.
+        if args.compat && args.export_futures {
+            return Err(Error::new(
+                Span::call_site(),
+                "compat and export_futures cannot be used together",
+            ));
+        }
+
+        Ok(args)
+    }
+}
+
+fn parse_lit<T: Parse>(input: ParseStream, expected: &str) -> Result<T> {
+    let lit: LitStr = input.parse()?;
+    lit.parse()
+        .map_err(|_| Error::new(lit.span(), format!("expected {}", expected)))
+}
+
+// `future_send_bound = "T: MySendLike"` where T stands for the future.
+fn parse_future_bound(input: ParseStream) -> Result<Bounds> {
+    let predicate: WherePredicate = parse_lit(input, "a where-clause predicate")?;
+    match predicate {
+        WherePredicate::Type(PredicateType {
+            lifetimes: None,
+            bounded_ty: Type::Path(ref ty),
+            bounds,
+            ..
+        }) if ty.qself.is_none() && ty.path.is_ident("T") => Ok(bounds),
+        predicate => Err(Error::new_spanned(
+            predicate,
+            "expected a bound on `T`, which stands for the returned future",
+        )),
+    }
+}
+
+// Matches `Send` and `Sync`, also as `std::marker::Send` or
+// `core::marker::Send`.
+fn auto_trait(bound: &TypeParamBound) -> Option<&'static str> {
+    let path = match bound {
+        TypeParamBound::Trait(bound) => &bound.path,
+        TypeParamBound::Lifetime(_) => return None,
+    };
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let name = match segments.as_slice() {
+        [name] => name,
+        [krate, module, name] if (krate == "std" || krate == "core") && module == "marker" => name,
+        _ => return None,
+    };
+    match name.as_str() {
+        "Send" => Some("Send"),
+        "Sync" => Some("Sync"),
+        _ => None,
+    }
+}
@@ -1,15 +1,18 @@
-use crate::lifetime::CollectLifetimes;
+use crate::args::Args;
+use crate::impl_trait::ImplTraitParams;
+use crate::lifetime::{CollectLifetimes, ElidedOutput};
 use crate::parse::Item;
-use crate::receiver::ReplaceReceiver;
-use proc_macro2::{Span, TokenStream};
-use quote::{quote, ToTokens};
+use crate::receiver::{is_typed_self, receiver_bounds, ReplaceReceiver};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned, ToTokens};
 use std::mem;
+use syn::parse::{Error, Result};
 use syn::punctuated::Punctuated;
-use syn::visit_mut::VisitMut;
+use syn::visit_mut::{self, VisitMut};
 use syn::{
-    parse_quote, ArgCaptured, ArgSelfRef, Block, FnArg, GenericParam, Generics, Ident, ImplItem,
-    Lifetime, MethodSig, Pat, PatIdent, Path, ReturnType, Token, TraitItem, Type, TypeParamBound,
-    WhereClause,
+    parse_quote, ArgCaptured, ArgSelfRef, Attribute, Block, FnArg, GenericParam, Generics, Ident,
+    ImplItem, Lifetime, MethodSig, Pat, PatIdent, Path, ReturnType, Token, TraitItem, Type,
+    TypeParamBound, WhereClause, WherePredicate,
 };
 
 impl ToTokens for Item {
@@ -37,42 +40,388 @@ enum Context<'a> {
 
 type Supertraits = Punctuated<TypeParamBound, Token![+]>;
 
-pub fn expand(input: &mut Item, is_local: bool) {
+pub fn expand(input: &mut Item, args: &Args) -> Result<()> {
+    if let Item::Impl(_) = input {
+        reject_trait_args(args)?;
+    }
+
+    // Leave traits and impls without any async methods exactly as written,
+    // e.g. when #[async_trait] is applied unconditionally by another macro.
+    if !has_async_methods(input) {
+        return Ok(());
+    }
+
     match input {
         Item::Trait(input) => {
+            check_reserved_lifetime(&input.generics, args)?;
+            let mut args = args.clone();
+            if args.export_futures {
+                args.box_future_type = Some(future_alias(&input.ident).into());
+            }
+            let args = &args;
+            let must_use = trait_must_use(&input.attrs);
             let context = Context::Trait {
                 name: &input.ident,
                 generics: &input.generics,
                 supertraits: &input.supertraits,
             };
+            // Methods that are not async, with or without a default body, are
+            // left untouched. Mixing them with async methods is legitimate.
             for inner in &mut input.items {
                 if let TraitItem::Method(method) = inner {
                     if method.sig.asyncness.is_some() {
+                        check_reserved_lifetime(&method.sig.decl.generics, args)?;
+                        strip_rust_abi(&mut method.sig)?;
+                        reject_async_recursion(&method.attrs)?;
+                        reject_weak_receiver(&method.sig)?;
+                        let args = &method_args(&mut method.attrs, args);
+                        if let Some(attr) = must_use {
+                            if trait_must_use(&method.attrs).is_none() {
+                                method.attrs.push(attr.clone());
+                            }
+                        }
+                        desugar_impl_trait(&mut method.sig);
+                        name_elided_output(&mut method.sig);
+                        add_bounds(&mut method.sig, args);
+                        if let Some(block) = &method.default {
+                            if args.lint_trivial_async {
+                                reject_trivial_async(&method.sig, block)?;
+                            }
+                        }
+                        let uses_self = match &method.default {
+                            Some(block) => static_uses_self(&method.sig, block),
+                            None => false,
+                        };
                         if let Some(block) = &mut method.default {
-                            transform_block(context, &method.sig, block, is_local);
+                            transform_block(context, &method.sig, block, args);
                         }
                         let has_default = method.default.is_some();
-                        transform_sig(context, &mut method.sig, has_default, is_local);
+                        transform_sig(context, &mut method.sig, has_default, uses_self, args);
                     }
                 }
             }
         }
         Item::Impl(input) => {
+            check_reserved_lifetime(&input.generics, args)?;
+            // `impl Trait for dyn A + Send` needs parentheses for the receiver
+            // to appear as `&(dyn A + Send)` in the inner fn.
+            let receiver = match &*input.self_ty {
+                ty @ Type::TraitObject(_) => parse_quote!((#ty)),
+                ty => ty.clone(),
+            };
             let context = Context::Impl {
                 impl_generics: &input.generics,
-                receiver: &input.self_ty,
+                receiver: &receiver,
                 as_trait: &input.trait_.as_ref().unwrap().1,
             };
             for inner in &mut input.items {
                 if let ImplItem::Method(method) = inner {
                     if method.sig.asyncness.is_some() {
-                        transform_block(context, &method.sig, &mut method.block, is_local);
-                        transform_sig(context, &mut method.sig, false, is_local);
+                        check_reserved_lifetime(&method.sig.decl.generics, args)?;
+                        strip_rust_abi(&mut method.sig)?;
+                        reject_async_recursion(&method.attrs)?;
+                        reject_weak_receiver(&method.sig)?;
+                        let args = &method_args(&mut method.attrs, args);
+                        desugar_impl_trait(&mut method.sig);
+                        name_elided_output(&mut method.sig);
+                        add_bounds(&mut method.sig, args);
+                        transform_block(context, &method.sig, &mut method.block, args);
+                        transform_sig(context, &mut method.sig, false, false, args);
                     }
                 }
             }
         }
     }
+    Ok(())
+}
+
+fn has_async_methods(input: &Item) -> bool {
+    match input {
+        Item::Trait(input) => input.items.iter().any(|item| match item {
+            TraitItem::Method(method) => method.sig.asyncness.is_some(),
+            _ => false,
+        }),
+        Item::Impl(input) => input.items.iter().any(|item| match item {
+            ImplItem::Method(method) => method.sig.asyncness.is_some(),
+            _ => false,
+        }),
+    }
+}
+
+// With #[async_trait(export_futures)], a trait `Trait` comes with
+//
+//     pub type TraitFuture<'async_trait, T> =
+//         Pin<Box<dyn Future<Output = T> + Send + 'async_trait>>;
+//
+// and its methods are declared in terms of that alias. Like the rest of the
+// expansion, it is left out for traits without async methods, which means it
+// has to be generated before the methods lose their `async`.
+pub fn export_futures(input: &Item, args: &Args) -> TokenStream {
+    let input = match input {
+        Item::Trait(trait_item) if args.export_futures && has_async_methods(input) => trait_item,
+        _ => return TokenStream::new(),
+    };
+
+    let vis = &input.vis;
+    let alias = future_alias(&input.ident);
+    let lifetime = args.future_lifetime();
+    let boxed = box_path(args, Span::call_site());
+    let send = if args.local {
+        None
+    } else {
+        let bounds = args.send_bounds();
+        Some(quote!(+ #bounds))
+    };
+    let doc = format!("Future returned by the async methods of `{}`.", input.ident);
+
+    quote! {
+        #[doc = #doc]
+        #vis type #alias<#lifetime, T> = ::core::pin::Pin<#boxed<
+            dyn ::core::future::Future<Output = T> #send + #lifetime
+        >>;
+    }
+}
+
+fn future_alias(trait_ident: &Ident) -> Ident {
+    Ident::new(&format!("{}Future", trait_ident), trait_ident.span())
+}
+
+// With #[async_trait(compat)] the item is emitted twice: once as written, for
+// compilers with native async fn in traits, and once expanded as usual. The
+// feature is the one of the crate being expanded, so crates implementing the
+// trait have to forward it from the crate defining it.
+//
+//     #[cfg(feature = "native-async-trait")]
+//     trait Trait { async fn f(&self); }
+//
+//     #[cfg(not(feature = "native-async-trait"))]
+//     trait Trait { fn f<'life0, 'async_trait>(&'life0 self) -> Pin<Box<...>>; }
+pub fn compat(native: &Item, expanded: TokenStream) -> TokenStream {
+    let native = without_method_args(native);
+    quote! {
+        #[cfg(feature = "native-async-trait")]
+        #native
+        #[cfg(not(feature = "native-async-trait"))]
+        #expanded
+    }
+}
+
+// The native item has no use for the `#[local]` and `#[send]` attributes that
+// only #[async_trait] understands.
+fn without_method_args(input: &Item) -> TokenStream {
+    fn retain(attrs: &mut Vec<Attribute>) {
+        attrs.retain(|attr| !attr.path.is_ident("local") && !attr.path.is_ident("send"));
+    }
+
+    match input {
+        Item::Trait(input) => {
+            let mut input = input.clone();
+            for inner in &mut input.items {
+                if let TraitItem::Method(method) = inner {
+                    retain(&mut method.attrs);
+                }
+            }
+            input.into_token_stream()
+        }
+        Item::Impl(input) => {
+            let mut input = input.clone();
+            for inner in &mut input.items {
+                if let ImplItem::Method(method) = inner {
+                    retain(&mut method.attrs);
+                }
+            }
+            input.into_token_stream()
+        }
+    }
+}
+
+// The generated signatures introduce their own 'async_trait lifetime, which
+// would silently clash with a user-declared lifetime of the same name.
+fn check_reserved_lifetime(generics: &Generics, args: &Args) -> Result<()> {
+    let reserved = args.future_lifetime();
+    for param in generics.lifetimes() {
+        if param.lifetime == reserved {
+            let msg = format!(
+                "the lifetime name `{}` is reserved by #[async_trait], \
+                 choose another with #[async_trait(lifetime = \"'...\")]",
+                reserved,
+            );
+            return Err(Error::new_spanned(&param.lifetime, msg));
+        }
+    }
+    Ok(())
+}
+
+// Predicates from #[async_trait(bound = "...")], for bounds the macro cannot
+// infer such as `Self::Item: Send` when an associated type is held across an
+// await.
+fn add_bounds(sig: &mut MethodSig, args: &Args) {
+    if !args.bounds.is_empty() {
+        let where_clause = sig.decl.generics.make_where_clause();
+        where_clause.predicates.extend(args.bounds.iter().cloned());
+    }
+}
+
+// `extern "Rust"` is the default ABI and can simply be dropped. Any other ABI
+// has no meaning for a method that returns a boxed Rust future.
+fn strip_rust_abi(sig: &mut MethodSig) -> Result<()> {
+    if let Some(abi) = &sig.abi {
+        match &abi.name {
+            Some(name) if name.value() == "Rust" => sig.abi = None,
+            _ => {
+                return Err(Error::new_spanned(
+                    abi,
+                    "async trait methods cannot use a non-Rust ABI",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// #[async_recursion] would expand after us and find a method that is no
+// longer async. It is also unnecessary since our futures are boxed already.
+fn reject_async_recursion(attrs: &[Attribute]) -> Result<()> {
+    for attr in attrs {
+        let last = attr.path.segments.last().unwrap();
+        if last.value().ident == "async_recursion" {
+            return Err(Error::new_spanned(
+                attr,
+                "#[async_recursion] is unnecessary in #[async_trait], \
+                 the returned futures are already boxed and may recurse",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Weak is not Deref, so `self: Weak<Self>` is not a valid receiver even with
+// arbitrary_self_types. Say so at the receiver instead of leaving rustc to
+// complain about the expanded signature.
+fn reject_weak_receiver(sig: &MethodSig) -> Result<()> {
+    let arg = match sig.decl.inputs.iter().next() {
+        Some(FnArg::Captured(arg)) => arg,
+        _ => return Ok(()),
+    };
+    let is_weak = match &arg.ty {
+        Type::Path(ty) if ty.qself.is_none() => {
+            ty.path.segments.last().unwrap().value().ident == "Weak"
+        }
+        _ => false,
+    };
+    match &arg.pat {
+        Pat::Ident(pat) if pat.ident == "self" && is_weak => Err(Error::new_spanned(
+            &arg.ty,
+            "`Weak<Self>` is not a valid receiver, \
+             take `Arc<Self>` and downgrade it in the body instead",
+        )),
+        _ => Ok(()),
+    }
+}
+
+// The alias and the lint concern the trait definition, an impl has nothing
+// to apply them to.
+fn reject_trait_args(args: &Args) -> Result<()> {
+    let trait_only = [
+        ("export_futures", args.export_futures),
+        ("lint_trivial_async", args.lint_trivial_async),
+    ];
+    for (name, given) in &trait_only {
+        if *given {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("{} can only be used on a trait, not on an impl", name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Opt-in check for default bodies that never await, whose boxed future is
+// pure overhead.
+fn reject_trivial_async(sig: &MethodSig, block: &Block) -> Result<()> {
+    if mentions_await(quote!(#block)) {
+        return Ok(());
+    }
+    Err(Error::new(
+        sig.asyncness.unwrap().span,
+        format!(
+            "default body of async fn `{}` contains no .await, consider a plain fn",
+            sig.ident,
+        ),
+    ))
+}
+
+// Both `.await` and the older `await!(...)` contain the keyword.
+fn mentions_await(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ident) => ident == "await",
+        TokenTree::Group(group) => mentions_await(group.stream()),
+        _ => false,
+    })
+}
+
+// #[must_use] on a trait only concerns `impl Trait` and `dyn Trait` values, so
+// it has to be repeated on each method for the message to show up when one of
+// the returned futures is dropped.
+fn trait_must_use(attrs: &[Attribute]) -> Option<&Attribute> {
+    attrs.iter().find(|attr| attr.path.is_ident("must_use"))
+}
+
+// A `#[local]` or `#[send]` attribute on an individual method overrides the
+// setting of the surrounding #[async_trait] or #[async_trait(local)].
+fn method_args(attrs: &mut Vec<Attribute>, outer: &Args) -> Args {
+    let mut args = outer.clone();
+    attrs.retain(|attr| {
+        if attr.path.is_ident("local") {
+            args.local = true;
+            false
+        } else if attr.path.is_ident("send") {
+            args.local = false;
+            false
+        } else {
+            true
+        }
+    });
+    // A named alias, exported or not, has the Send-ness of the trait rather
+    // than of this method, so spell out the boxed type instead.
+    if args.local != outer.local {
+        args.box_future_type = None;
+    }
+    args
+}
+
+// Argument-position impl Trait becomes a named type parameter, identically in
+// the trait and in the impls, so that the inner fn can be given explicit
+// generic arguments. The same goes for impl Trait in the return type of a
+// bound like `F: Fn(&Self) -> impl Future<Output = R>`, which the compiler
+// would otherwise reject.
+fn desugar_impl_trait(sig: &mut MethodSig) {
+    let mut impl_trait = ImplTraitParams::new();
+    for arg in sig.decl.inputs.iter_mut() {
+        if let FnArg::Captured(arg) = arg {
+            impl_trait.visit_type_mut(&mut arg.ty);
+        }
+    }
+    for param in sig.decl.generics.params.iter_mut() {
+        if let GenericParam::Type(param) = param {
+            for bound in param.bounds.iter_mut() {
+                impl_trait.visit_type_param_bound_mut(bound);
+            }
+        }
+    }
+    if let Some(where_clause) = &mut sig.decl.generics.where_clause {
+        for predicate in where_clause.predicates.iter_mut() {
+            if let WherePredicate::Type(predicate) = predicate {
+                for bound in predicate.bounds.iter_mut() {
+                    impl_trait.visit_type_param_bound_mut(bound);
+                }
+            }
+        }
+    }
+    for param in impl_trait.params {
+        sig.decl.generics.params.push(GenericParam::Type(param));
+    }
 }
 
 // Input:
@@ -88,7 +437,13 @@ pub fn expand(input: &mut Item, is_local: bool) {
 //         'life1: 'async_trait,
 //         T: 'async_trait,
 //         Self: Sync + 'async_trait;
-fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_local: bool) {
+fn transform_sig(
+    context: Context,
+    sig: &mut MethodSig,
+    has_default: bool,
+    uses_self: bool,
+    args: &Args,
+) {
     sig.decl.fn_token.span = sig.asyncness.take().unwrap().span;
 
     let ret = match &sig.decl.output {
@@ -98,7 +453,8 @@ fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_lo
 
     let has_self = match sig.decl.inputs.iter_mut().next() {
         Some(FnArg::SelfRef(_)) | Some(FnArg::SelfValue(_)) => true,
-        _ => false,
+        Some(arg) => is_typed_self(arg),
+        None => false,
     };
 
     let mut elided = CollectLifetimes::new();
@@ -112,7 +468,7 @@ fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_lo
 
     let lifetime: Lifetime;
     if !sig.decl.generics.params.is_empty() || !elided.lifetimes.is_empty() || has_self {
-        lifetime = parse_quote!('async_trait);
+        lifetime = args.future_lifetime();
         let where_clause = sig
             .decl
             .generics
@@ -146,28 +502,56 @@ fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_lo
         }
         sig.decl.generics.params.push(parse_quote!(#lifetime));
         if has_self {
-            let bound: Ident = match &sig.decl.inputs[0] {
+            let receiver: Type = match &sig.decl.inputs[0] {
                 FnArg::SelfRef(ArgSelfRef {
                     mutability: None, ..
-                }) => parse_quote!(Sync),
-                _ => parse_quote!(Send),
+                }) => parse_quote!(&Self),
+                FnArg::SelfRef(_) => parse_quote!(&mut Self),
+                FnArg::Captured(arg) => arg.ty.clone(),
+                _ => parse_quote!(Self),
             };
-            let assume_bound = match context {
-                Context::Trait { supertraits, .. } => {
-                    !has_default || has_bound(supertraits, &bound)
+            let mut bounds = receiver_bounds(&receiver, &args.future_markers());
+            match context {
+                Context::Trait {
+                    supertraits,
+                    generics,
+                    ..
+                } if has_default && !args.local => {
+                    bounds.retain(|bound| !has_bound(supertraits, generics, bound));
                 }
+                _ => bounds.clear(),
+            }
+            // Self: 'async_trait goes without saying if the trait requires
+            // Self: 'static.
+            let outlives = match context {
+                Context::Trait {
+                    supertraits,
+                    generics,
+                    ..
+                } => !has_static_bound(supertraits, generics),
                 Context::Impl { .. } => true,
             };
-            where_clause.predicates.push(if assume_bound {
-                parse_quote!(Self: #lifetime)
-            } else {
-                parse_quote!(Self: core::marker::#bound + #lifetime)
-            });
+            if outlives {
+                where_clause
+                    .predicates
+                    .push(parse_quote!(Self: #(::core::marker::#bounds +)* #lifetime));
+            } else if !bounds.is_empty() {
+                where_clause
+                    .predicates
+                    .push(parse_quote!(Self: #(::core::marker::#bounds)+*));
+            }
         }
     } else {
         lifetime = parse_quote!('static);
     };
 
+    // The future of a default method without receiver captures Self only
+    // through its type, but that is enough to require Self: 'async_trait.
+    if uses_self {
+        let where_clause = sig.decl.generics.make_where_clause();
+        where_clause.predicates.push(parse_quote!(Self: #lifetime));
+    }
+
     for (i, arg) in sig.decl.inputs.iter_mut().enumerate() {
         match arg {
             FnArg::SelfRef(_) => {}
@@ -187,19 +571,54 @@ fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_lo
         }
     }
 
-    let bounds: Supertraits = if is_local {
-        parse_quote!(#lifetime)
-    } else {
-        parse_quote!(#lifetime + core::marker::Send)
-    };
+    if let Some(box_future_type) = &args.box_future_type {
+        sig.decl.output = parse_quote!(-> #box_future_type<#lifetime, #ret>);
+        return;
+    }
+
+    let mut bounds: Supertraits = parse_quote!(#lifetime);
+    if !args.local {
+        bounds.extend(args.send_bounds());
+    }
+
+    if args.impl_future {
+        sig.decl.output = parse_quote! {
+            -> impl ::core::future::Future<Output = #ret> + #bounds
+        };
+        return;
+    }
 
+    let boxed = box_path(args, Span::call_site());
     sig.decl.output = parse_quote! {
-        -> core::pin::Pin<Box<
-            dyn core::future::Future<Output = #ret> + #bounds
+        -> ::core::pin::Pin<#boxed<
+            dyn ::core::future::Future<Output = #ret> + #bounds
         >>
     };
 }
 
+// `&self -> &T` takes the lifetime of self for the return type, but elision
+// stops applying once the generated signature has several lifetimes, so name
+// it explicitly.
+fn name_elided_output(sig: &mut MethodSig) {
+    let receiver = match sig.decl.inputs.iter_mut().next() {
+        Some(FnArg::SelfRef(receiver)) => receiver,
+        _ => return,
+    };
+    let lifetime = match &receiver.lifetime {
+        Some(lifetime) => lifetime.clone(),
+        None => Lifetime::new("'life_self", Span::call_site()),
+    };
+    let mut output = ElidedOutput::new(lifetime);
+    if let ReturnType::Type(_, ret) = &mut sig.decl.output {
+        output.visit_type_mut(ret);
+    }
+    if output.found && receiver.lifetime.is_none() {
+        let lifetime = output.lifetime;
+        receiver.lifetime = Some(lifetime.clone());
+        sig.decl.generics.params.insert(0, parse_quote!(#lifetime));
+    }
+}
+
 // Input:
 //     async fn f<T>(&self, x: &T) -> Ret {
 //         self + x
@@ -209,10 +628,12 @@ fn transform_sig(context: Context, sig: &mut MethodSig, has_default: bool, is_lo
 //     async fn f<T, AsyncTrait>(_self: &AsyncTrait, x: &T) -> Ret {
 //         _self + x
 //     }
-//     Pin::from(Box::new(async_trait_method::<T, Self>(self, x)))
-fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, is_local: bool) {
+//     Box::pin(async_trait_method::<T, Self>(self, x))
+fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, args: &Args) {
     let inner = Ident::new(&format!("__{}", sig.ident), sig.ident.span());
-    let args = sig
+    let self_ident = receiver_ident(sig);
+    let uses_self = static_uses_self(sig, block);
+    let call_args = sig
         .decl
         .inputs
         .iter()
@@ -226,6 +647,12 @@ fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, is_loca
             _ => positional_arg(i).into_token_stream(),
         });
 
+    // Attributes of the original method, like #[inline], #[cold] or
+    // #[track_caller], stay on the outer method. The inner fn has a single
+    // caller, and #[track_caller] on an async fn is a no-op on stable. Lint
+    // attributes like #[allow(unused_variables)] need not be copied either,
+    // because lint levels are lexically scoped and the inner fn sits in the
+    // outer body.
     let mut standalone = sig.clone();
     standalone.ident = inner.clone();
 
@@ -244,18 +671,12 @@ fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, is_loca
             .extend(where_clause.predicates);
     }
 
+    let lifetime = args.future_lifetime();
     standalone
         .decl
         .generics
         .params
-        .push(parse_quote!('async_trait));
-
-    let mut types = standalone
-        .decl
-        .generics
-        .type_params()
-        .map(|param| param.ident.clone())
-        .collect::<Vec<_>>();
+        .push(parse_quote!(#lifetime));
 
     match standalone.decl.inputs.iter_mut().next() {
         Some(arg @ FnArg::SelfRef(_)) => {
@@ -269,49 +690,75 @@ fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, is_loca
             };
             match context {
                 Context::Trait { name, generics, .. } => {
-                    let bound = match mutability {
-                        Some(_) => quote!(Send),
-                        None => quote!(Sync),
+                    let bounds = if args.local {
+                        Vec::new()
+                    } else {
+                        receiver_bounds(&parse_quote!(&#mutability Self), &args.future_markers())
                     };
                     *arg = parse_quote! {
-                        _self: &#lifetime #mutability AsyncTrait
+                        #self_ident: &#lifetime #mutability AsyncTrait
                     };
                     let (_, generics, _) = generics.split_for_impl();
-                    if is_local {
-                        standalone.decl.generics.params.push(parse_quote! {
-                            AsyncTrait: ?Sized + #name #generics
-                        });
-                    } else {
-                        standalone.decl.generics.params.push(parse_quote! {
-                            AsyncTrait: ?Sized + #name #generics + core::marker::#bound
-                        });
-                    }
-                    types.push(Ident::new("Self", Span::call_site()));
+                    standalone.decl.generics.params.push(parse_quote! {
+                        AsyncTrait: ?::core::marker::Sized + #name #generics #(+ ::core::marker::#bounds)*
+                    });
                 }
                 Context::Impl { receiver, .. } => {
                     *arg = parse_quote! {
-                        _self: &#lifetime #mutability #receiver
+                        #self_ident: &#lifetime #mutability #receiver
                     };
                 }
             }
         }
         Some(arg @ FnArg::SelfValue(_)) => match context {
             Context::Trait { name, generics, .. } => {
+                let bounds = if args.local {
+                    Vec::new()
+                } else {
+                    receiver_bounds(&parse_quote!(Self), &args.future_markers())
+                };
                 *arg = parse_quote! {
-                    _self: AsyncTrait
+                    #self_ident: AsyncTrait
                 };
                 let (_, generics, _) = generics.split_for_impl();
                 standalone.decl.generics.params.push(parse_quote! {
-                    AsyncTrait: ?Sized + #name #generics + core::marker::Send
+                    AsyncTrait: ?::core::marker::Sized + #name #generics #(+ ::core::marker::#bounds)*
                 });
-                types.push(Ident::new("Self", Span::call_site()));
             }
             Context::Impl { receiver, .. } => {
                 *arg = parse_quote! {
-                    _self: #receiver
+                    #self_ident: #receiver
                 };
             }
         },
+        Some(FnArg::Captured(ArgCaptured {
+            pat: Pat::Ident(pat),
+            ty,
+            ..
+        })) if pat.ident == "self" => {
+            pat.ident = Ident::new(&self_ident.to_string(), pat.ident.span());
+            if let Context::Trait { name, generics, .. } = context {
+                let (_, generics, _) = generics.split_for_impl();
+                let bounds = if args.local {
+                    Vec::new()
+                } else {
+                    receiver_bounds(ty, &args.future_markers())
+                };
+                standalone.decl.generics.params.push(parse_quote! {
+                    AsyncTrait: ?::core::marker::Sized + #name #generics #(+ ::core::marker::#bounds)*
+                });
+            }
+        }
+        _ if uses_self => {
+            // Without a receiver the body can still refer to `Self`, as in
+            // `Self::new()`, which becomes `AsyncTrait::new()` below.
+            if let Context::Trait { name, generics, .. } = context {
+                let (_, generics, _) = generics.split_for_impl();
+                standalone.decl.generics.params.push(parse_quote! {
+                    AsyncTrait: ?::core::marker::Sized + #name #generics
+                });
+            }
+        }
         _ => {}
     }
 
@@ -319,34 +766,193 @@ fn transform_block(context: Context, sig: &MethodSig, block: &mut Block, is_loca
         // Work around an input bound like `where Self::Output: Send` expanding
         // to `where <AsyncTrait>::Output: Send` which is illegal syntax because
         // `where<T>` is reserved for future use... :(
-        where_clause.predicates.insert(0, parse_quote!((): Sized));
+        where_clause
+            .predicates
+            .insert(0, parse_quote!((): ::core::marker::Sized));
     }
 
     let mut replace = match context {
-        Context::Trait { .. } => ReplaceReceiver::with(parse_quote!(AsyncTrait)),
+        Context::Trait { .. } => ReplaceReceiver::with(parse_quote!(AsyncTrait), self_ident),
         Context::Impl {
             receiver, as_trait, ..
-        } => ReplaceReceiver::with_as_trait(receiver.clone(), as_trait.clone()),
+        } => ReplaceReceiver::with_as_trait(receiver.clone(), as_trait.clone(), self_ident),
     };
     replace.visit_method_sig_mut(&mut standalone);
     replace.visit_block_mut(block);
 
+    // Syn only parses type parameters ahead of const parameters.
+    let (consts, params): (Vec<GenericParam>, Vec<GenericParam>) = standalone
+        .decl
+        .generics
+        .params
+        .iter()
+        .cloned()
+        .partition(|param| matches!(param, GenericParam::Const(_)));
+    standalone.decl.generics.params = params.into_iter().chain(consts).collect();
+
+    let types = standalone
+        .decl
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) if param.ident == "AsyncTrait" => {
+                Some(Ident::new("Self", Span::call_site()))
+            }
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            GenericParam::Const(param) => Some(param.ident.clone()),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect::<Vec<_>>();
+
+    // Errors about the returned future, like it not being Send, point at the
+    // method name rather than at the #[async_trait] attribute.
+    let span = sig.ident.span();
+    let mut call = quote_spanned!(span=> #inner::<#(#types),*>(#(#call_args),*));
+    if standalone.unsafety.is_some() {
+        call = quote_spanned!(span=> unsafe { #call });
+    }
+
+    // The Box path needs the span too: rustc reports the failed coercion at
+    // the span of the whole `Box::pin(...)` expression, which falls back to
+    // the attribute if its tokens come from different places.
+    if !args.impl_future {
+        let boxed = box_path(args, span);
+        call = quote_spanned!(span=> #boxed::pin(#call));
+    }
+
+    // The inner future is only ever not Send in local methods, where that is
+    // the point, as anywhere else the coercion to `dyn Future + Send` fails.
+    // The 'async_trait parameter goes unused when nothing is borrowed.
     let brace = block.brace_token;
     *block = parse_quote!({
+        #[allow(
+            dead_code,
+            clippy::async_yields_async,
+            clippy::extra_unused_lifetimes,
+            clippy::future_not_send,
+            clippy::unused_async,
+        )]
         #standalone #block
-        core::pin::Pin::from(Box::new(#inner::<#(#types),*>(#(#args),*)))
+        #call
     });
     block.brace_token = brace;
 }
 
+// Whether a method without receiver mentions `Self` in its signature or body.
+fn static_uses_self(sig: &MethodSig, block: &Block) -> bool {
+    let has_receiver = match sig.decl.inputs.iter().next() {
+        Some(FnArg::SelfRef(_)) | Some(FnArg::SelfValue(_)) => true,
+        Some(arg) => is_typed_self(arg),
+        None => false,
+    };
+    !has_receiver && mentions_self(quote!(#sig #block))
+}
+
+fn mentions_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(ident) => ident == "Self",
+        TokenTree::Group(group) => mentions_self(group.stream()),
+        _ => false,
+    })
+}
+
+// The receiver becomes `_self` in the inner fn, with more leading underscores
+// if the method already has an argument by that name.
+fn receiver_ident(sig: &MethodSig) -> Ident {
+    let mut bindings = PatBindings(Vec::new());
+    for arg in &sig.decl.inputs {
+        if let FnArg::Captured(arg) = arg {
+            bindings.visit_pat_mut(&mut arg.pat.clone());
+        }
+    }
+    let mut name = String::from("_self");
+    while bindings.0.iter().any(|ident| *ident == name) {
+        name.insert(0, '_');
+    }
+    Ident::new(&name, Span::call_site())
+}
+
+// Every identifier bound by a pattern, including those nested in tuple and
+// struct patterns like `(_self, y): (u8, u8)`.
+struct PatBindings(Vec<Ident>);
+
+impl VisitMut for PatBindings {
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        self.0.push(pat.ident.clone());
+        visit_mut::visit_pat_ident_mut(self, pat);
+    }
+}
+
+// Box is not in the prelude of no_std crates. The paths are absolute for
+// modules with #![no_implicit_prelude].
+fn box_path(args: &Args, span: Span) -> TokenStream {
+    if args.no_std {
+        quote_spanned!(span=> ::alloc::boxed::Box)
+    } else {
+        quote_spanned!(span=> ::std::boxed::Box)
+    }
+}
+
 fn positional_arg(i: usize) -> Ident {
     Ident::new(&format!("__arg{}", i), Span::call_site())
 }
 
-fn has_bound(supertraits: &Supertraits, marker: &Ident) -> bool {
-    for bound in supertraits {
+// Whether `Self: Sync` or similar is already implied by the trait, either as a
+// supertrait or as a `where Self: Sync` clause on the trait. Bounds implied by
+// supertraits of supertraits are not visible here and get emitted anyway.
+fn has_bound(supertraits: &Supertraits, generics: &Generics, marker: &Ident) -> bool {
+    implied_self_bounds(supertraits, generics).any(|bounds| is_marker_bound(bounds, marker))
+}
+
+fn has_static_bound(supertraits: &Supertraits, generics: &Generics) -> bool {
+    implied_self_bounds(supertraits, generics).any(|bounds| {
+        bounds.iter().any(|bound| match bound {
+            TypeParamBound::Lifetime(lifetime) => lifetime.ident == "static",
+            TypeParamBound::Trait(_) => false,
+        })
+    })
+}
+
+fn implied_self_bounds<'a>(
+    supertraits: &'a Supertraits,
+    generics: &'a Generics,
+) -> impl Iterator<Item = &'a Supertraits> {
+    let predicates = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| &where_clause.predicates)
+        .filter_map(|predicate| match predicate {
+            WherePredicate::Type(predicate) => match &predicate.bounded_ty {
+                Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident("Self") => {
+                    Some(&predicate.bounds)
+                }
+                _ => None,
+            },
+            _ => None,
+        });
+    Some(supertraits).into_iter().chain(predicates)
+}
+
+// Matches `Sync`, `std::marker::Sync` and `core::marker::Sync`.
+fn is_marker_bound(bounds: &Supertraits, marker: &Ident) -> bool {
+    for bound in bounds {
         if let TypeParamBound::Trait(bound) = bound {
-            if bound.path.is_ident(marker.clone()) {
+            let segments: Vec<String> = bound
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            let marker = marker.to_string();
+            let matches = match segments.as_slice() {
+                [name] => *name == marker,
+                [krate, module, name] => {
+                    (krate == "std" || krate == "core") && module == "marker" && *name == marker
+                }
+                _ => false,
+            };
+            if matches {
                 return true;
             }
         }
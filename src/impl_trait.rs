@@ -0,0 +1,35 @@
+use proc_macro2::Span;
+use syn::visit_mut::{self, VisitMut};
+use syn::{parse_quote, Ident, Type, TypeParam};
+
+// `x: impl Trait` -> `x: __ImplTrait0` with a type parameter `__ImplTrait0:
+// Trait`, so that the parameter can be forwarded to the inner fn.
+pub struct ImplTraitParams {
+    pub params: Vec<TypeParam>,
+}
+
+impl ImplTraitParams {
+    pub fn new() -> Self {
+        ImplTraitParams { params: Vec::new() }
+    }
+}
+
+impl VisitMut for ImplTraitParams {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        // `&mut (impl Trait)` -> `&mut __ImplTrait0` without the parentheses.
+        if let Type::Paren(paren) = ty {
+            if let Type::ImplTrait(_) = &*paren.elem {
+                let elem = (*paren.elem).clone();
+                *ty = elem;
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+        if let Type::ImplTrait(impl_trait) = ty {
+            let name = format!("__ImplTrait{}", self.params.len());
+            let ident = Ident::new(&name, Span::call_site());
+            let bounds = &impl_trait.bounds;
+            self.params.push(parse_quote!(#ident: #bounds));
+            *ty = parse_quote!(#ident);
+        }
+    }
+}
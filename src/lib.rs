@@ -95,21 +95,444 @@
 //! Not all async traits need Send/Sync TypeBounds. To avoid having them placed
 //! on your generated methods, annotate your traits as `#[async_trait(local)]`
 //!
+//! Individual methods can opt out of the trait-level setting with `#[local]`
+//! inside an `#[async_trait]` trait, or `#[send]` inside an
+//! `#[async_trait(local)]` trait. The same attribute must be repeated on the
+//! method in each impl.
+//!
+//! An impl cannot be more lenient than its trait, so a method that some impl
+//! needs to run with `Rc` or `RefCell` state has to be local in the trait.
+//!
+//! ```
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! # use std::rc::Rc;
+//! #
+//! #[async_trait]
+//! trait Widget {
+//!     async fn cpu_work(&self);
+//!
+//!     #[local]
+//!     async fn ui_work(&self, state: Rc<String>);
+//! }
+//! ```
+//!
+//! Whether a method is local is never inferred from what its body captures.
+//!
+//! The most common way to end up with a future that is not Send is holding a
+//! `std::sync::MutexGuard` across an `.await`. The compiler reports this as
+//! "future cannot be sent between threads safely" on the method name, with a
+//! note pointing at the guard and the await. Drop the guard before awaiting,
+//! or use an async-aware mutex such as `tokio::sync::Mutex` whose guard is
+//! Send.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! # use std::sync::Mutex;
+//! #
+//! # async fn flush() {}
+//! #
+//! #[async_trait]
+//! trait Log {
+//!     async fn write(&self, line: &str);
+//! }
+//!
+//! struct Buffered(Mutex<Vec<String>>);
+//!
+//! #[async_trait]
+//! impl Log for Buffered {
+//!     async fn write(&self, line: &str) {
+//!         let mut lines = self.0.lock().unwrap();
+//!         lines.push(line.to_owned());
+//!         flush().await;
+//!     }
+//! }
+//! ```
+//!
+//! ```text
+//! error: future cannot be sent between threads safely
+//!   --> src/main.rs:12:14
+//!    |
+//! 12 |     async fn write(&self, line: &str) {
+//!    |              ^^^^^ future returned by `__write` is not `Send`
+//! ```
+//!
+//! Likewise, an impl for a type that is not Sync fails on its `&self` methods
+//! with "cannot be shared between threads safely"; making the trait or those
+//! methods `local` drops the Sync bound on `Self` along with the Send bound.
+//!
+//! Methods taking `&mut self` or `self` only require `Self: Send`, so types
+//! that are Send but not Sync can still implement those. A `Copy` receiver
+//! does not change this, as the body still uses `self` through a reference.
+//!
+//! ## Extra bounds
+//! Whether a future is Send can depend on an associated type, for example
+//! when a default method holds a `Self::Item` across an await. Such
+//! predicates can be added to the where-clause of every async method with
+//! `#[async_trait(bound = "Self::Item: Send")]`, repeated for more than one.
+//!
+//! ```
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait(bound = "Self::Item: Send")]
+//! trait Source {
+//!     type Item;
+//!
+//!     async fn next(&self) -> Self::Item;
+//!     async fn ready(&self);
+//!
+//!     async fn next_when_ready(&self) -> Self::Item {
+//!         let item = self.next().await;
+//!         self.ready().await;
+//!         item
+//!     }
+//! }
+//! ```
+//!
+//! ## Named future types
+//! Crates that already have a type alias for boxed futures, such as
+//! `futures::future::BoxFuture`, can have the generated methods spelled in
+//! terms of it with `#[async_trait(box_future_type = "futures::future::BoxFuture")]`.
+//! The alias is expected to take a lifetime and an output type, in that order.
+//! Combine with `local` and a non-Send alias like
+//! `futures::future::LocalBoxFuture` for traits whose futures are not Send.
+//! Methods marked `#[local]` or `#[send]` against the trait's setting return
+//! the spelled-out boxed type instead, since the alias cannot change its
+//! bounds per method.
+//!
+//! With `#[async_trait(export_futures)]` the macro instead defines such an
+//! alias next to the trait, named after it and with the trait's visibility,
+//! so that the futures of `trait Fetch` can be named as
+//! `FetchFuture<'a, T>` in where-clauses and annotations elsewhere.
+//!
+//! ## Custom Send bound
+//! `#[async_trait(future_send_bound = "T: MySendLike")]` puts the given bounds
+//! on the returned future in place of `Send`, with `T` standing for the
+//! future type. A plain `dyn Future` can only carry auto traits, so without
+//! `impl_future` the bounds are limited to `Send` and `Sync` and anything else
+//! is an error. The bounds on `Self` that default methods need follow the
+//! ones given, e.g. only `Self: Sync` for a by-value receiver with
+//! `"T: Sync"`, while a custom marker trait is assumed to need what `Send`
+//! needs. Together with `local` there is no bound at all.
+//!
+//! ## Unpin
+//! The returned `Pin<Box<dyn Future + Send + 'async_trait>>` is already
+//! `Unpin`, so it can be passed directly to `select!` and other combinators
+//! that require it.
+//!
+//! ## Unboxed futures
+//! Traits that are never used as trait objects can avoid the allocation with
+//! `#[async_trait(impl_future)]`. The generated methods then return `impl
+//! Future<Output = T> + Send + 'async_trait` instead of a boxed future, and
+//! the compiler rejects any attempt to make a `dyn Trait` out of the trait.
+//! This is the only allocation-free mode; there is none based on `pin-project`.
+//! Methods that call themselves recursively need the default boxed futures,
+//! because an unboxed future type cannot contain itself.
+//!
+//! ## Migrating to native async fn in traits
+//! `#[async_trait(compat)]` emits the trait or impl twice: as written, under
+//! `#[cfg(feature = "native-async-trait")]`, and expanded as usual under the
+//! negation of that. A library can then declare a `native-async-trait`
+//! feature of its own so that users on newer compilers opt into the native
+//! syntax while everyone else keeps the boxed futures.
+//!
+//! The `cfg` is evaluated in whichever crate the macro expands in, not in the
+//! crate defining the trait. Every crate with a `#[async_trait(compat)]`
+//! impl of the trait therefore needs a `native-async-trait` feature too, and
+//! must enable it exactly when it is enabled on the library, typically by
+//! forwarding it as `native-async-trait = ["the-library/native-async-trait"]`
+//! and never enabling the library's feature directly. An impl expanded the
+//! other way does not match the trait.
+//!
+//! The `#[local]` and `#[send]` method attributes are dropped from the native
+//! version. Note that natively async traits are not dyn-capable, and that
+//! their futures carry no Send bound, so switching the feature on is a
+//! breaking change for users who rely on either.
+//!
+//! ## No-std
+//! The generated code refers to `::std::boxed::Box` by its full path, so a
+//! `Box` of your own in scope does not interfere. In a `#![no_std]` crate with
+//! an allocator, write `#[async_trait(no_std)]` to get `::alloc::boxed::Box`
+//! instead. The crate root must contain `extern crate alloc;` for that path to
+//! resolve.
+//!
+//! ## Blanket impls
+//! The future returned by an impl method captures the receiver, so in a
+//! blanket impl the type parameter needs whichever marker trait makes that
+//! receiver `Send`: `Sync` for `&self`, `Send` for `&mut self` and `self`.
+//! Unless the trait already has these as supertraits, spell them out on the
+//! impl.
+//!
+//! ```
+//! # use async_trait::async_trait;
+//! #
+//! pub trait Repository {
+//!     fn get(&self) -> u8;
+//! }
+//!
+//! #[async_trait]
+//! pub trait Service {
+//!     async fn call(&self) -> u8;
+//!     async fn call_mut(&mut self) -> u8;
+//! }
+//!
+//! #[async_trait]
+//! impl<T: Repository + Send + Sync> Service for T {
+//!     async fn call(&self) -> u8 {
+//!         self.get()
+//!     }
+//!
+//!     async fn call_mut(&mut self) -> u8 {
+//!         self.get()
+//!     }
+//! }
+//! ```
+//!
+//! ## Lifetime name
+//! The generated methods name the lifetime of the returned future
+//! `'async_trait`, so neither the methods nor the trait or impl can declare a
+//! lifetime of their own with that name. Doing so is an error pointing at the
+//! declaration.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait]
+//! trait Parser<'async_trait> {
+//!     async fn parse(&self, input: &'async_trait str);
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait]
+//! trait Cache {
+//!     async fn get<'async_trait>(&'async_trait self, key: &str) -> &'async_trait str;
+//! }
+//! ```
+//!
+//! ```text
+//! error: the lifetime name `'async_trait` is reserved by #[async_trait], choose another with #[async_trait(lifetime = "'...")]
+//!  --> src/main.rs:3:18
+//!   |
+//! 3 |     async fn get<'async_trait>(&'async_trait self, key: &str) -> &'async_trait str;
+//!   |                  ^^^^^^^^^^^^
+//! ```
+//!
+//! Use for example `#[async_trait(lifetime = "'fut")]` on the trait and its
+//! impls to pick a different name.
+//!
+//! ```
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait(lifetime = "'fut")]
+//! trait Cache {
+//!     async fn get<'async_trait>(&'async_trait self, key: &str) -> &'async_trait str;
+//! }
+//!
+//! struct Empty;
+//!
+//! #[async_trait(lifetime = "'fut")]
+//! impl Cache for Empty {
+//!     async fn get<'async_trait>(&'async_trait self, _key: &str) -> &'async_trait str {
+//!         ""
+//!     }
+//! }
+//! ```
+//!
+//! ## Other attribute macros
+//! Attribute macros run from the outermost inward, so put #\[async_trait\]
+//! first when stacking it with attributes like `#[typetag::serde]`. The
+//! attributes below it then see ordinary methods returning boxed futures
+//! rather than `async fn`, which trait-object oriented macros already
+//! understand. The same order has to be used on the trait and on its impls.
+//!
+//! Attribute macros on individual methods, such as `#[tracing::instrument]`,
+//! are emitted unchanged and in their original order on the generated
+//! method, and so expand after #\[async_trait\]. They see a plain `fn`
+//! whose body holds the original one as an inner `async fn` and returns its
+//! future boxed, as `::std::boxed::Box::pin(__method::<...>(...))`. The
+//! inner fn is always named after the method with two leading underscores,
+//! but the rest of the expression varies: the Box path is under `::alloc`
+//! with `no_std`, the call is wrapped in `unsafe {}` for unsafe methods, and
+//! there is no Box at all with `impl_future`. A macro like `#[instrument]`
+//! therefore sees the wrapper, whose span only covers constructing the
+//! future; put it on a function called from the method body to have it
+//! cover the actual work.
+//! The exception is `#[async_recursion]`, which is rejected: it expects an
+//! `async fn`, and the boxed futures can recurse without it.
+//!
+//! Other attributes like `#[inline]` and `#[track_caller]` stay on the wrapper
+//! too. In particular `#[track_caller]` does not reach the body: a panic in
+//! the body reports its own location rather than the caller of the method.
+//!
+//! Macros that generate a whole trait machinery of their own, like
+//! `#[salsa::query_group]`, should not be stacked with #\[async_trait\]; put
+//! the async methods in a separate trait with the generated one as supertrait.
+//!
+//! `mockall`'s `#[automock]` recognizes #\[async_trait\] below it and mocks
+//! the methods with their plain output types, so no special mode is needed.
+//!
+//! ## Unused futures
+//! `#[must_use]` on an async method, with or without a message, is kept on
+//! the generated method, so dropping the returned future without awaiting it
+//! triggers the `unused_must_use` lint with that message.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! # #![deny(unused_must_use)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait]
+//! trait Cache {
+//!     #[must_use = "the entry is only removed once the future is awaited"]
+//!     async fn evict(&self, key: u32);
+//! }
+//!
+//! fn evict_all(cache: &dyn Cache) {
+//!     cache.evict(1);
+//! }
+//! ```
+//!
+//! ```text
+//! error: unused return value of `Cache::evict` that must be used
+//!   --> src/main.rs:10:5
+//!    |
+//! 10 |     cache.evict(1);
+//!    |     ^^^^^^^^^^^^^^^
+//!    |
+//!    = note: the entry is only removed once the future is awaited
+//! ```
+//!
+//! `#[must_use]` on the trait itself only concerns `impl Trait` and
+//! `dyn Trait` values, so it is repeated on each async method that does not
+//! have one of its own.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! # #![deny(unused_must_use)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait]
+//! #[must_use = "the returned futures do nothing unless awaited"]
+//! trait Queue {
+//!     async fn push(&self, job: u32);
+//! }
+//!
+//! fn enqueue(queue: &dyn Queue) {
+//!     queue.push(1);
+//! }
+//! ```
+//!
+//! ## Trivially async methods
+//! `#[async_trait(lint_trivial_async)]` on a trait rejects default method
+//! bodies that contain no `.await`, since those could be a plain `fn` without
+//! the boxed future. Proc macros cannot emit warnings on stable, so this is
+//! reported as an error and is off by default.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait(lint_trivial_async)]
+//! trait Config {
+//!     async fn retries(&self) -> u32 {
+//!         3
+//!     }
+//! }
+//! ```
+//!
+//! ```text
+//! error: default body of async fn `retries` contains no .await, consider a plain fn
+//!  --> src/main.rs:3:5
+//!   |
+//! 3 |     async fn retries(&self) -> u32 {
+//!   |     ^^^^^
+//! ```
+//!
+//! ## Debugging
+//! `#[async_trait(debug)]` prints the expanded trait or impl to stderr during
+//! compilation, for inspecting the generated code without external tools.
+//!
+//! Lifetime errors can mention `'life0`, `'life1` and so on, the elided
+//! lifetimes of the arguments in order, which must outlive `'async_trait`.
+//!
+//! In backtraces and profiles the method bodies appear as
+//! `<S as Trait>::method::__method::{closure#0}`.
 //!
 //! It is the intention that all features of Rust traits should work nicely with
 //! #\[async_trait\], but the edge cases are numerous. Please file an issue if
-//! you see unexpected borrow checker errors, type errors, or warnings. There is
-//! no use of `unsafe` in the expanded code, so rest assured that if your code
-//! compiles it can't be that badly broken.
-//!
-//! > &#9745;&emsp;Self by value, by reference, by mut reference, or no self;<br>
+//! you see unexpected borrow checker errors, type errors, or warnings. The
+//! expanded code uses `unsafe` only for methods that are themselves declared
+//! `unsafe`, so rest assured that if your code compiles it can't be that badly
+//! broken. The inner fn of such a method stays an `unsafe fn`, so that its
+//! body is an unsafe context just as written, and is called from an `unsafe`
+//! block.
+//!
+//! > &#9745;&emsp;Self by value, by reference, by mut reference, by
+//! > `Box<Self>`, `Arc<Self>`, `&Arc<Self>`, `Rc<Self>` in local traits or
+//! > `Pin<Box<Self>>`, or no self;<br>
 //! > &#9745;&emsp;Any number of arguments, any return value;<br>
+//! > &#9745;&emsp;`impl Trait` arguments, and `impl Trait` return types on
+//! > compilers that support them in traits;<br>
 //! > &#9745;&emsp;Generic type parameters and lifetime parameters;<br>
 //! > &#9745;&emsp;Associated types;<br>
 //! > &#9745;&emsp;Having async and non-async functions in the same trait;<br>
 //! > &#9745;&emsp;Default implementations provided by the trait;<br>
 //! > &#9745;&emsp;Elided lifetimes;<br>
-//! > &#9745;&emsp;Dyn-capable traits.<br>
+//! > &#9745;&emsp;Dyn-capable traits;<br>
+//! > &#9745;&emsp;Attributes like `#[must_use]` on the generated methods.<br>
+//!
+//! `self: Weak<Self>` is not a supported receiver, since `Weak` does not
+//! implement `Deref`, and the macro reports an error for it. Take
+//! `self: Arc<Self>` and call `Arc::downgrade` in the body instead.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! # use std::sync::Weak;
+//! #
+//! #[async_trait]
+//! trait Cache {
+//!     async fn invalidate(self: Weak<Self>);
+//! }
+//! ```
+//!
+//! Applied to anything other than a trait or a trait impl, such as a free
+//! async fn, the attribute reports an error at that item.
+//!
+//! ```compile_fail
+//! # #![feature(async_await)]
+//! #
+//! # use async_trait::async_trait;
+//! #
+//! #[async_trait]
+//! async fn connect(url: &str) {}
+//! ```
+//!
+//! ```text
+//! error: async_trait can only be applied to trait or impl blocks
+//!  --> src/main.rs:2:1
+//!   |
+//! 2 | async fn connect(url: &str) {}
+//!   | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+//! ```
 //!
 //! <br>
 //!
@@ -134,12 +557,25 @@
 //!             /* the original method body */
 //!         }
 //!
-//!         Pin::from(Box::new(run(self)))
+//!         Box::pin(run(self))
 //!     }
 //! }
 //! # };
 //! ```
 //!
+//! The box is what lets every impl of the trait return the same type and keeps
+//! the trait dyn-capable; there is no expansion based on generic associated
+//! types.
+//!
+//! The trait objects and the `dyn Future` they return have no stable ABI, so
+//! they cannot be passed across an FFI boundary.
+//!
+//! Methods written out in this expanded form, as a plain `fn` returning
+//! `Pin<Box<dyn Future>>`, are not async and are therefore left alone. An
+//! `async fn` whose declared return type is itself a boxed future is
+//! transformed like any other: awaiting it produces that inner future, just
+//! as it would for an async fn outside of a trait.
+//!
 //! <br><br>
 //!
 //! # Elided lifetimes
@@ -304,25 +740,39 @@
 
 extern crate proc_macro;
 
+mod args;
 mod expand;
+mod impl_trait;
 mod lifetime;
 mod parse;
 mod receiver;
 
-use crate::expand::expand;
+use crate::args::Args;
+use crate::expand::{compat, expand, export_futures};
 use crate::parse::Item;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse_macro_input;
 
-mod kw {
-    syn::custom_keyword!(local);
-}
-
 #[proc_macro_attribute]
 pub fn async_trait(args: TokenStream, input: TokenStream) -> TokenStream {
-    let local = parse_macro_input!(args as Option<kw::local>).is_some();
+    let args = parse_macro_input!(args as Args);
     let mut item = parse_macro_input!(input as Item);
-    expand(&mut item, local);
-    TokenStream::from(quote!(#item))
+    let native = if args.compat {
+        Some(item.clone())
+    } else {
+        None
+    };
+    let aliases = export_futures(&item, &args);
+    if let Err(err) = expand(&mut item, &args) {
+        return TokenStream::from(err.to_compile_error());
+    }
+    let mut expanded = quote!(#item #aliases);
+    if let Some(native) = native {
+        expanded = compat(&native, expanded);
+    }
+    if args.debug {
+        eprintln!("{}", expanded);
+    }
+    TokenStream::from(expanded)
 }
@@ -1,6 +1,8 @@
 use proc_macro2::Span;
 use syn::visit_mut::{self, VisitMut};
-use syn::{ArgSelfRef, GenericArgument, Lifetime, TypeReference};
+use syn::{
+    ArgSelfRef, GenericArgument, Lifetime, ParenthesizedGenericArguments, TypeBareFn, TypeReference,
+};
 
 pub struct CollectLifetimes {
     pub lifetimes: Vec<Lifetime>,
@@ -51,3 +53,47 @@ impl VisitMut for CollectLifetimes {
         visit_mut::visit_generic_argument_mut(self, gen);
     }
 }
+
+// Fills in the elided lifetimes of a return type with a given lifetime.
+pub struct ElidedOutput {
+    pub lifetime: Lifetime,
+    pub found: bool,
+}
+
+impl ElidedOutput {
+    pub fn new(lifetime: Lifetime) -> Self {
+        ElidedOutput {
+            lifetime,
+            found: false,
+        }
+    }
+}
+
+impl VisitMut for ElidedOutput {
+    fn visit_type_reference_mut(&mut self, ty: &mut TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = Some(self.lifetime.clone());
+            self.found = true;
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_generic_argument_mut(&mut self, gen: &mut GenericArgument) {
+        if let GenericArgument::Lifetime(lifetime) = gen {
+            if lifetime.ident == "_" {
+                *lifetime = self.lifetime.clone();
+                self.found = true;
+            }
+        }
+        visit_mut::visit_generic_argument_mut(self, gen);
+    }
+
+    // `fn(&u8) -> &u8` and `Fn(&u8) -> &u8` have elision scopes of their own.
+    fn visit_type_bare_fn_mut(&mut self, _ty: &mut TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments_mut(
+        &mut self,
+        _args: &mut ParenthesizedGenericArguments,
+    ) {
+    }
+}
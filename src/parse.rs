@@ -1,6 +1,6 @@
 use proc_macro2::Span;
 use syn::parse::{Error, Parse, ParseStream, Result};
-use syn::{Attribute, ItemImpl, ItemTrait, Token};
+use syn::{ItemImpl, ItemTrait};
 
 pub struct Nothing;
 
@@ -10,6 +10,7 @@ impl Parse for Nothing {
     }
 }
 
+#[derive(Clone)]
 pub enum Item {
     Trait(ItemTrait),
     Impl(ItemImpl),
@@ -17,21 +18,18 @@ pub enum Item {
 
 impl Parse for Item {
     fn parse(input: ParseStream) -> Result<Self> {
-        let attrs = input.call(Attribute::parse_outer)?;
-        let lookahead = input.lookahead1();
-        if lookahead.peek(Token![pub]) || lookahead.peek(Token![trait]) {
-            let mut item: ItemTrait = input.parse()?;
-            item.attrs = attrs;
-            Ok(Item::Trait(item))
-        } else if lookahead.peek(Token![impl]) {
-            let mut item: ItemImpl = input.parse()?;
-            if item.trait_.is_none() {
-                return Err(Error::new(Span::call_site(), "expected a trait impl"));
+        match input.parse()? {
+            syn::Item::Trait(item) => Ok(Item::Trait(item)),
+            syn::Item::Impl(item) => {
+                if item.trait_.is_none() {
+                    return Err(Error::new(Span::call_site(), "expected a trait impl"));
+                }
+                Ok(Item::Impl(item))
             }
-            item.attrs = attrs;
-            Ok(Item::Impl(item))
-        } else {
-            Err(lookahead.error())
+            item => Err(Error::new_spanned(
+                item,
+                "async_trait can only be applied to trait or impl blocks",
+            )),
         }
     }
 }
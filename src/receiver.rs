@@ -1,25 +1,92 @@
+use proc_macro2::Span;
 use std::mem;
 use syn::punctuated::Punctuated;
 use syn::visit_mut::{self, VisitMut};
-use syn::{parse_quote, ExprPath, Item, Path, QSelf, Type, TypePath};
+use syn::{
+    ArgCaptured, ExprPath, FnArg, GenericArgument, Ident, Item, Pat, Path, PathArguments, QSelf,
+    Type, TypePath,
+};
+
+// An explicitly typed receiver like `self: Box<Self>`.
+pub fn is_typed_self(arg: &FnArg) -> bool {
+    match arg {
+        FnArg::Captured(ArgCaptured {
+            pat: Pat::Ident(pat),
+            ..
+        }) => pat.ident == "self",
+        _ => false,
+    }
+}
+
+// Marker traits that Self must implement in order for a receiver to have the
+// given markers, e.g. `Send + Sync` for `self: Arc<Self>` to be Send or `Sync`
+// for `&self` to be Send.
+pub fn receiver_bounds(ty: &Type, markers: &[&'static str]) -> Vec<Ident> {
+    let mut bounds = Vec::new();
+    for marker in markers {
+        collect_bounds(ty, marker, &mut bounds);
+    }
+    bounds
+        .into_iter()
+        .map(|bound| Ident::new(bound, Span::call_site()))
+        .collect()
+}
+
+fn collect_bounds(ty: &Type, marker: &'static str, bounds: &mut Vec<&'static str>) {
+    match ty {
+        Type::Reference(ty) => {
+            let marker = if ty.mutability.is_some() {
+                marker
+            } else {
+                "Sync"
+            };
+            collect_bounds(&ty.elem, marker, bounds);
+        }
+        Type::Path(ty) if ty.qself.is_none() => {
+            let last = ty.path.segments.last().unwrap().into_value();
+            if last.ident == "Self" {
+                if !bounds.contains(&marker) {
+                    bounds.push(marker);
+                }
+                return;
+            }
+            if let PathArguments::AngleBracketed(arguments) = &last.arguments {
+                for arg in &arguments.args {
+                    if let GenericArgument::Type(arg) = arg {
+                        if last.ident == "Arc" {
+                            collect_bounds(arg, "Send", bounds);
+                            collect_bounds(arg, "Sync", bounds);
+                        } else {
+                            collect_bounds(arg, marker, bounds);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
 
 pub struct ReplaceReceiver {
     pub with: Type,
     pub as_trait: Option<Path>,
+    pub self_ident: Ident,
 }
 
 impl ReplaceReceiver {
-    pub fn with(ty: Type) -> Self {
+    pub fn with(ty: Type, self_ident: Ident) -> Self {
         ReplaceReceiver {
             with: ty,
             as_trait: None,
+            self_ident,
         }
     }
 
-    pub fn with_as_trait(ty: Type, as_trait: Path) -> Self {
+    pub fn with_as_trait(ty: Type, as_trait: Path, self_ident: Ident) -> Self {
         ReplaceReceiver {
             with: ty,
             as_trait: Some(as_trait),
+            self_ident,
         }
     }
 
@@ -92,7 +159,7 @@ impl VisitMut for ReplaceReceiver {
     fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
         if expr.qself.is_none() {
             if expr.path.is_ident("self") {
-                expr.path.segments[0].ident = parse_quote!(_self);
+                expr.path.segments[0].ident = self.self_ident.clone();
             }
             self.self_to_qself_expr(&mut expr.qself, &mut expr.path);
         }
@@ -0,0 +1,41 @@
+#![feature(arbitrary_self_types, async_await)]
+
+use async_trait::async_trait;
+
+struct Struct;
+
+pub async fn test_custom_smart_pointer_receiver() {
+    use std::ops::Deref;
+
+    struct Ptr<T: ?Sized>(Box<T>);
+
+    impl<T: ?Sized> Deref for Ptr<T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            &self.0
+        }
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn required(self: Ptr<Self>) -> u8;
+
+        async fn provided(self: Ptr<Self>) -> u8
+        where
+            Self: Sized,
+        {
+            self.required().await
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(self: Ptr<Self>) -> u8 {
+            let _: Ptr<Struct> = self;
+            0
+        }
+    }
+
+    Ptr(Box::new(Struct)).provided().await;
+}
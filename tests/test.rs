@@ -1,5 +1,7 @@
 #![feature(async_await)]
 
+extern crate alloc;
+
 use async_trait::async_trait;
 
 #[async_trait]
@@ -131,6 +133,2318 @@ pub async fn test_object_no_send() {
     object.f().await;
 }
 
+pub async fn test_local_method() {
+    use std::rc::Rc;
+
+    #[async_trait]
+    trait Trait {
+        async fn send(&self);
+
+        #[local]
+        async fn local(&self) {
+            let rc = Rc::new(());
+            async {}.await;
+            drop(rc);
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn send(&self) {}
+
+        #[local]
+        async fn local(&self) {
+            let rc = Rc::new(());
+            async {}.await;
+            drop(rc);
+        }
+    }
+
+    Struct.send().await;
+    Struct.local().await;
+}
+
+pub async fn test_send_method() {
+    #[async_trait(local)]
+    trait Trait {
+        async fn local(&self);
+
+        #[send]
+        async fn send(&self);
+    }
+
+    #[async_trait(local)]
+    impl Trait for Struct {
+        async fn local(&self) {}
+
+        #[send]
+        async fn send(&self) {}
+    }
+
+    fn assert_send<T: Send>(_: T) {}
+    assert_send(Struct.send());
+    Struct.local().await;
+}
+
+pub async fn test_local_default_not_sync() {
+    use std::cell::Cell;
+
+    #[async_trait]
+    trait Method {
+        #[local]
+        async fn local(&self) -> u8 {
+            0
+        }
+    }
+
+    #[async_trait(local)]
+    trait Local {
+        async fn by_ref(&self) -> u8 {
+            0
+        }
+
+        async fn by_value(self) -> u8
+        where
+            Self: Sized,
+        {
+            0
+        }
+    }
+
+    struct NotSync(Cell<u8>);
+
+    #[async_trait]
+    impl Method for NotSync {}
+
+    #[async_trait(local)]
+    impl Local for NotSync {}
+
+    let value = NotSync(Cell::new(0));
+    value.0.set(1);
+    value.local().await;
+    value.by_ref().await;
+    value.by_value().await;
+}
+
+pub mod box_future_type {
+    use async_trait::async_trait;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::rc::Rc;
+
+    pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+    pub type LocalBoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+    #[async_trait(box_future_type = "BoxFuture")]
+    pub trait Trait {
+        async fn f(&self, x: &str) -> usize;
+
+        #[local]
+        async fn local(&self, x: Rc<str>) -> usize;
+    }
+
+    #[async_trait(box_future_type = "BoxFuture")]
+    impl Trait for super::Struct {
+        async fn f(&self, x: &str) -> usize {
+            x.len()
+        }
+
+        #[local]
+        async fn local(&self, x: Rc<str>) -> usize {
+            async {}.await;
+            x.len()
+        }
+    }
+
+    #[async_trait(local, box_future_type = "self::LocalBoxFuture")]
+    pub trait Local {
+        async fn f(&self, x: &str) -> usize;
+
+        #[send]
+        async fn send(&self) -> usize;
+    }
+
+    #[async_trait(local, box_future_type = "self::LocalBoxFuture")]
+    impl Local for super::Struct {
+        async fn f(&self, x: &str) -> usize {
+            x.len()
+        }
+
+        #[send]
+        async fn send(&self) -> usize {
+            0
+        }
+    }
+
+    pub fn test_send(object: &dyn Local) -> impl Future<Output = usize> + Send + '_ {
+        object.send()
+    }
+
+    pub fn test(object: &dyn Trait) -> BoxFuture<'_, usize> {
+        object.f("")
+    }
+
+    pub fn test_local(object: &dyn Local) -> LocalBoxFuture<'_, usize> {
+        object.f("")
+    }
+}
+
+pub async fn test_no_std() {
+    #[async_trait(no_std)]
+    trait Trait {
+        async fn f(&self, x: &str) -> usize {
+            x.len()
+        }
+    }
+
+    #[async_trait(no_std)]
+    impl Trait for Struct {}
+
+    Struct.f("").await;
+}
+
+pub async fn test_inline() {
+    #[async_trait]
+    trait Trait {
+        async fn required(&self);
+
+        #[inline(always)]
+        async fn provided(&self) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        #[inline]
+        async fn required(&self) {}
+    }
+
+    Struct.required().await;
+    Struct.provided().await;
+}
+
+#[deny(unused_must_use)]
+pub async fn test_must_use() {
+    #[async_trait]
+    trait Trait {
+        #[must_use = "futures do nothing unless you .await them"]
+        async fn required(&self) -> usize;
+
+        #[must_use]
+        async fn provided(&self) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(&self) -> usize {
+            0
+        }
+    }
+
+    let _ = Struct.required().await;
+    Struct.provided().await;
+
+    let object = &Struct as &dyn Trait;
+    let _ = object.required().await;
+}
+
+#[allow(deprecated)]
+pub async fn test_deprecated() {
+    #[async_trait]
+    trait Trait: Sync {
+        #[deprecated(since = "0.1.3", note = "use `g` instead")]
+        async fn f(&self) {}
+
+        async fn g(&self);
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn g(&self) {}
+    }
+
+    let object = &Struct as &dyn Trait;
+    object.f().await;
+    object.g().await;
+}
+
+pub async fn test_track_caller() {
+    #[async_trait]
+    trait Trait {
+        #[track_caller]
+        async fn required(&self);
+
+        #[track_caller]
+        async fn provided(&self) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        #[track_caller]
+        async fn required(&self) {}
+    }
+
+    Struct.required().await;
+    Struct.provided().await;
+}
+
+pub async fn test_cold() {
+    #[async_trait]
+    trait Trait {
+        async fn required(&self);
+
+        #[cold]
+        async fn provided(&self) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        #[cold]
+        async fn required(&self) {}
+    }
+
+    Struct.required().await;
+    Struct.provided().await;
+}
+
+#[deny(unsafe_op_in_unsafe_fn)]
+pub async fn test_unsafe() {
+    #[async_trait]
+    trait Trait {
+        async unsafe fn required(&self, bytes: &[u8]) -> u8;
+
+        async unsafe fn provided(&self, bytes: &[u8]) -> u8
+        where
+            Self: Sync,
+        {
+            unsafe { *bytes.get_unchecked(0) }
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async unsafe fn required(&self, bytes: &[u8]) -> u8 {
+            unsafe { *bytes.get_unchecked(0) }
+        }
+    }
+
+    unsafe {
+        Struct.required(&[0]).await;
+        Struct.provided(&[0]).await;
+    }
+}
+
+pub async fn test_box_self() {
+    #[async_trait]
+    trait ObjectSafe {
+        async fn consume(self: Box<Self>);
+    }
+
+    #[async_trait]
+    impl ObjectSafe for Struct {
+        async fn consume(self: Box<Self>) {}
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn f(self: Box<Self>) -> usize;
+
+        async fn g(mut self: Box<Self>) -> usize {
+            self.calls().await;
+            self.f().await
+        }
+
+        async fn calls(&mut self) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn f(self: Box<Self>) -> usize {
+            0
+        }
+
+        async fn g(mut self: Box<Self>) -> usize {
+            self.calls().await;
+            self.f().await
+        }
+    }
+
+    let object: Box<dyn ObjectSafe> = Box::new(Struct);
+    object.consume().await;
+
+    Box::new(Struct).g().await;
+}
+
+pub async fn test_arc_self() {
+    use std::sync::Arc;
+
+    #[async_trait]
+    trait Trait {
+        async fn required(self: Arc<Self>);
+
+        async fn provided(self: Arc<Self>) {
+            let clone = Arc::clone(&self);
+            clone.required().await;
+            self.required().await;
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(self: Arc<Self>) {}
+    }
+
+    #[async_trait]
+    trait ObjectSafe: Send + Sync {
+        async fn shared(self: Arc<Self>) {}
+    }
+
+    #[async_trait]
+    impl ObjectSafe for Struct {}
+
+    Arc::new(Struct).provided().await;
+
+    let object: Arc<dyn ObjectSafe> = Arc::new(Struct);
+    object.shared().await;
+}
+
+pub async fn test_pin_box_self() {
+    use std::pin::Pin;
+
+    #[async_trait]
+    trait Trait {
+        async fn required(self: Pin<Box<Self>>);
+
+        async fn provided(self: Pin<Box<Self>>) {
+            self.required().await;
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(self: Pin<Box<Self>>) {}
+    }
+
+    #[async_trait]
+    trait ObjectSafe {
+        async fn pinned(self: Pin<Box<Self>>);
+    }
+
+    #[async_trait]
+    impl ObjectSafe for Struct {
+        async fn pinned(self: Pin<Box<Self>>) {}
+    }
+
+    Box::pin(Struct).provided().await;
+
+    let object: Pin<Box<dyn ObjectSafe>> = Box::pin(Struct);
+    object.pinned().await;
+}
+
+pub async fn test_custom_lifetime() {
+    #[async_trait(lifetime = "'fut")]
+    trait Trait {
+        async fn f<'async_trait>(&self, x: &'async_trait str) -> &'async_trait str;
+
+        async fn g(&self) {}
+    }
+
+    #[async_trait(lifetime = "'fut")]
+    impl Trait for Struct {
+        async fn f<'async_trait>(&self, x: &'async_trait str) -> &'async_trait str {
+            x
+        }
+    }
+
+    Struct.f("").await;
+    Struct.g().await;
+}
+
+pub async fn test_cfg() {
+    #[async_trait]
+    trait Trait {
+        #[cfg(any())]
+        async fn disabled(&self) -> DoesNotExist;
+
+        #[cfg(not(any()))]
+        async fn enabled(&self);
+
+        #[cfg(target_os = "unknown")]
+        async fn provided(&self) -> DoesNotExist {
+            unimplemented!()
+        }
+
+        #[cfg(not(target_os = "unknown"))]
+        async fn provided(&self) {}
+
+        #[cfg_attr(all(), must_use)]
+        async fn cfg_attr(&self) -> usize {
+            0
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        #[cfg(any())]
+        async fn disabled(&self) -> DoesNotExist {
+            unimplemented!()
+        }
+
+        #[cfg(not(any()))]
+        async fn enabled(&self) {}
+    }
+
+    Struct.enabled().await;
+    Struct.provided().await;
+    let _ = Struct.cfg_attr().await;
+}
+
+pub async fn test_impl_trait_arg() {
+    #[async_trait]
+    trait Trait {
+        async fn required(&self, iter: impl Iterator<Item = u32> + Send) -> u32;
+
+        async fn provided(&self, iter: &mut (impl Iterator<Item = u32> + Send)) -> u32
+        where
+            Self: Sync,
+        {
+            iter.sum()
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(&self, iter: impl Iterator<Item = u32> + Send) -> u32 {
+            iter.sum()
+        }
+    }
+
+    Struct.required(vec![1, 2].into_iter()).await;
+    Struct.provided(&mut vec![1, 2].into_iter()).await;
+}
+
+pub async fn test_manually_boxed() {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    // Methods that already return a boxed future are written without `async`
+    // and are left alone by the macro, so the future is boxed only once.
+    #[async_trait]
+    trait Trait {
+        async fn expanded(&self) -> usize;
+
+        fn manual(&self) -> Pin<Box<dyn Future<Output = usize> + Send + '_>>;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn expanded(&self) -> usize {
+            self.manual().await
+        }
+
+        fn manual(&self) -> Pin<Box<dyn Future<Output = usize> + Send + '_>> {
+            Box::pin(async { 0 })
+        }
+    }
+
+    let object = &Struct as &dyn Trait;
+    object.expanded().await;
+    object.manual().await;
+}
+
+pub async fn test_impl_future() {
+    #[async_trait(impl_future)]
+    trait Trait {
+        async fn required(&self, x: &str) -> usize;
+
+        async fn provided(&self) -> usize {
+            self.required("").await
+        }
+    }
+
+    #[async_trait(impl_future)]
+    impl Trait for Struct {
+        async fn required(&self, x: &str) -> usize {
+            x.len()
+        }
+    }
+
+    fn assert_send<T: Send>(_: T) {}
+    assert_send(Struct.provided());
+    Struct.provided().await;
+}
+
+pub async fn test_const_generics() {
+    #[async_trait]
+    trait Trait<const M: usize> {
+        async fn fill<const N: usize>(&mut self, buf: &mut [u8; N]);
+
+        async fn mixed<'a, T, const FLAG: bool>(&self, x: &'a T) -> &'a T
+        where
+            Self: Sync,
+            T: Sync,
+        {
+            let _ = (M, FLAG);
+            x
+        }
+    }
+
+    #[async_trait]
+    impl Trait<2> for [u8; 2] {
+        async fn fill<const N: usize>(&mut self, buf: &mut [u8; N]) {
+            let n = N.min(2);
+            buf[..n].copy_from_slice(&self[..n]);
+        }
+    }
+
+    let mut buf = [0; 4];
+    [1; 2].fill(&mut buf).await;
+    [1; 2].mixed::<_, true>(&0).await;
+}
+
+pub async fn test_patterns() {
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn tuple(&self, (x, y): (i32, i32)) -> i32;
+
+        async fn nested(&self, (a, (b, _)): (i32, (i32, i32)), Point { x, y }: Point) -> i32
+        where
+            Self: Sync,
+        {
+            a + b + x + y
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn tuple(&self, (x, mut y): (i32, i32)) -> i32 {
+            y += x;
+            y
+        }
+    }
+
+    Struct.tuple((1, 2)).await;
+    Struct.nested((1, (2, 3)), Point { x: 4, y: 5 }).await;
+}
+
+pub async fn test_unsafe_trait() {
+    /// # Safety
+    ///
+    /// No invariant, the trait only checks that `unsafe` is preserved.
+    #[async_trait]
+    unsafe trait UnsafeTrait {
+        async fn f(&self);
+    }
+
+    #[async_trait]
+    unsafe impl UnsafeTrait for Struct {
+        async fn f(&self) {}
+    }
+
+    Struct.f().await;
+}
+
+#[deny(
+    dead_code,
+    clippy::async_yields_async,
+    clippy::extra_unused_lifetimes,
+    clippy::future_not_send,
+    clippy::unused_async
+)]
+pub async fn test_inner_fn_lints() {
+    use std::rc::Rc;
+
+    #[async_trait]
+    trait Trait {
+        async fn unused_async(&self) {}
+
+        #[local]
+        async fn not_send(&self, rc: Rc<u8>) -> u8 {
+            async {}.await;
+            *rc
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn unused_async(&self) {}
+    }
+
+    Struct.unused_async().await;
+    // Awaiting it would make this test's own future not Send.
+    drop(Struct.not_send(Rc::new(0)));
+}
+
+pub async fn test_recursion() {
+    #[async_trait]
+    trait Countdown {
+        async fn countdown(&self, n: usize) -> usize;
+    }
+
+    #[async_trait]
+    impl Countdown for Struct {
+        async fn countdown(&self, n: usize) -> usize {
+            if n == 0 {
+                0
+            } else {
+                1 + self.countdown(n - 1).await
+            }
+        }
+    }
+
+    Struct.countdown(3).await;
+}
+
+pub async fn test_ref_arc_self() {
+    use std::sync::Arc;
+
+    #[async_trait]
+    trait Trait {
+        async fn subtask(self: Arc<Self>);
+
+        async fn fanout(self: &Arc<Self>) {
+            Arc::clone(self).subtask().await;
+            Arc::clone(self).subtask().await;
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn subtask(self: Arc<Self>) {}
+
+        async fn fanout(self: &Arc<Self>) {
+            let _ = Arc::clone(self);
+        }
+    }
+
+    Arc::new(Struct).fanout().await;
+}
+
+pub async fn test_blanket_impl() {
+    trait Repository {
+        fn get(&self) -> u8;
+    }
+
+    #[async_trait]
+    trait Service {
+        async fn call(&self) -> u8;
+        async fn call_mut(&mut self) -> u8;
+        async fn call_once(self) -> u8;
+    }
+
+    #[async_trait]
+    impl<T: Repository + Send + Sync> Service for T {
+        async fn call(&self) -> u8 {
+            self.get()
+        }
+
+        async fn call_mut(&mut self) -> u8 {
+            self.get()
+        }
+
+        async fn call_once(self) -> u8 {
+            self.get()
+        }
+    }
+
+    impl Repository for Struct {
+        fn get(&self) -> u8 {
+            0
+        }
+    }
+
+    Struct.call().await;
+    Struct.call_mut().await;
+    Struct.call_once().await;
+}
+
+pub async fn test_where_self_clone() {
+    #[async_trait]
+    trait Retry {
+        async fn attempt(self) -> bool;
+
+        async fn retry(&self) -> bool
+        where
+            Self: Clone,
+        {
+            self.clone().attempt().await || self.clone().attempt().await
+        }
+    }
+
+    #[derive(Clone)]
+    struct Cloneable;
+
+    #[async_trait]
+    impl Retry for Cloneable {
+        async fn attempt(self) -> bool {
+            true
+        }
+    }
+
+    Cloneable.retry().await;
+}
+
+pub async fn test_arg_named_self() {
+    #[async_trait]
+    #[allow(clippy::duplicate_underscore_argument)]
+    trait Trait {
+        async fn f(&self, _self: u8) -> u8 {
+            let _ = self;
+            _self
+        }
+
+        async fn g(&self, (_self, y): (u8, u8)) -> u8 {
+            let _ = self;
+            _self + y
+        }
+    }
+
+    #[async_trait]
+    #[allow(clippy::duplicate_underscore_argument)]
+    impl Trait for Struct {
+        async fn f(&self, _self: u8) -> u8 {
+            let _ = self;
+            _self
+        }
+
+        async fn g(&self, (_self, y): (u8, u8)) -> u8 {
+            let _ = self;
+            _self + y
+        }
+    }
+
+    Struct.f(0).await;
+    Struct.g((0, 0)).await;
+}
+
+pub async fn test_elided_alias() {
+    type Elided<'a> = &'a str;
+    type Nested<'a, T> = Option<&'a [T]>;
+
+    #[async_trait]
+    trait Trait {
+        async fn elided(&self, s: Elided<'_>) -> usize {
+            s.len()
+        }
+
+        async fn nested(&self, s: Nested<'_, Elided<'_>>) -> usize {
+            s.map_or(0, <[_]>::len)
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn elided(&self, s: Elided<'_>) -> usize {
+            s.len()
+        }
+
+        async fn nested(&self, s: Nested<'_, Elided<'_>>) -> usize {
+            s.map_or(0, <[_]>::len)
+        }
+    }
+
+    Struct.elided("").await;
+    Struct.nested(None).await;
+}
+
+pub fn test_unpin() {
+    fn assert_unpin<T: Unpin>(_: T) {}
+
+    #[async_trait]
+    trait Trait {
+        async fn unpin(&self);
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn unpin(&self) {}
+    }
+
+    assert_unpin(Struct.unpin());
+}
+
+pub async fn test_macro_rules() {
+    #[async_trait]
+    trait Trait {
+        async fn get(&self) -> u8;
+        async fn get_with(&self, x: u8) -> u8;
+    }
+
+    macro_rules! impl_trait {
+        ($ty:ty, $value:expr) => {
+            #[async_trait]
+            impl Trait for $ty {
+                async fn get(&self) -> u8 {
+                    $value
+                }
+
+                async fn get_with(&self, x: u8) -> u8 {
+                    self.get().await + x
+                }
+            }
+        };
+    }
+
+    macro_rules! impl_trait_body {
+        ($ty:ty, $get:block) => {
+            #[async_trait]
+            impl Trait for $ty {
+                async fn get(&self) -> u8 $get
+
+                async fn get_with(&self, x: u8) -> u8 {
+                    self.get().await + x
+                }
+            }
+        };
+    }
+
+    struct Forwarded;
+
+    impl_trait!(Struct, 1);
+    impl_trait_body!(Forwarded, {
+        let _ = self;
+        2
+    });
+
+    Struct.get_with(1).await;
+    Forwarded.get_with(1).await;
+}
+
+pub async fn test_bound() {
+    #[async_trait(bound = "Self::Item: Send", bound = "Self::Error: Send")]
+    trait Source {
+        type Item;
+        type Error;
+
+        async fn next(&self) -> Result<Self::Item, Self::Error>;
+        async fn ready(&self);
+
+        async fn next_when_ready(&self) -> Result<Self::Item, Self::Error> {
+            let item = self.next().await;
+            self.ready().await;
+            item
+        }
+    }
+
+    #[async_trait]
+    impl Source for Struct {
+        type Item = u8;
+        type Error = ();
+
+        async fn next(&self) -> Result<u8, ()> {
+            Ok(0)
+        }
+
+        async fn ready(&self) {}
+    }
+
+    let _ = Struct.next_when_ready().await;
+}
+
+#[deny(warnings)]
+pub fn test_no_async_methods() {
+    #[async_trait]
+    trait Trait<'async_trait> {
+        type Assoc;
+
+        fn f(&self) -> &'async_trait str;
+
+        fn g(&self) -> usize {
+            self.f().len()
+        }
+    }
+
+    #[async_trait]
+    impl<'async_trait> Trait<'async_trait> for &'async_trait str {
+        type Assoc = ();
+
+        fn f(&self) -> &'async_trait str {
+            self
+        }
+    }
+
+    "".g();
+}
+
+pub async fn test_rust_abi() {
+    #[async_trait]
+    trait Trait {
+        async extern "Rust" fn f(&self);
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async extern "Rust" fn f(&self) {}
+    }
+
+    Struct.f().await;
+}
+
+#[deny(unused_variables, clippy::unused_async)]
+pub async fn test_lint_attrs() {
+    #[async_trait]
+    trait Trait {
+        #[allow(unused_variables)]
+        async fn f(&self, unused: u8) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        #[allow(unused_variables, clippy::unused_async)]
+        async fn f(&self, unused: u8) {}
+    }
+
+    Struct.f(0).await;
+}
+
+#[deny(unused_variables)]
+pub async fn test_underscore_args() {
+    #[async_trait]
+    trait Trait {
+        async fn f(&self, _x: u8, (_a, _b): (u8, u8)) {}
+
+        async fn g(self: Box<Self>, _: String) {}
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn f(&self, _x: u8, _: (u8, u8)) {}
+    }
+
+    Struct.f(0, (0, 0)).await;
+    Box::new(Struct).g(String::new()).await;
+}
+
+pub async fn test_poll_method() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    #[async_trait]
+    trait Trait {
+        fn poll_ready(&mut self, cx: &mut Context) -> Poll<()>;
+
+        async fn ready(&mut self);
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        fn poll_ready(&mut self, _cx: &mut Context) -> Poll<()> {
+            Poll::Ready(())
+        }
+
+        async fn ready(&mut self) {
+            poll_fn(|cx| self.poll_ready(cx)).await;
+        }
+    }
+
+    fn poll_fn<F>(f: F) -> impl Future<Output = ()>
+    where
+        F: FnMut(&mut Context) -> Poll<()> + Unpin,
+    {
+        struct PollFn<F>(F);
+
+        impl<F> Future for PollFn<F>
+        where
+            F: FnMut(&mut Context) -> Poll<()> + Unpin,
+        {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+                (self.get_mut().0)(cx)
+            }
+        }
+
+        PollFn(f)
+    }
+
+    Struct.ready().await;
+}
+
+/// Documented.
+#[deny(missing_docs)]
+pub mod docs {
+    use async_trait::async_trait;
+
+    /// Documented.
+    #[async_trait]
+    pub trait Trait {
+        /// Documented.
+        async fn f(&self);
+    }
+
+    /// Documented.
+    pub struct Struct;
+
+    #[async_trait]
+    impl Trait for Struct {
+        /// Documented.
+        async fn f(&self) {}
+    }
+}
+
+pub async fn test_impl_trait_return() {
+    use std::io::{self, Read, Write};
+
+    #[async_trait]
+    trait Trait {
+        async fn split(&self) -> (impl Read, impl Write);
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn split(&self) -> (impl Read, impl Write) {
+            (io::empty(), io::sink())
+        }
+    }
+
+    let (mut reader, mut writer) = Struct.split().await;
+    let _ = io::copy(&mut reader, &mut writer);
+}
+
+pub mod export_futures {
+    use async_trait::async_trait;
+
+    #[async_trait(export_futures)]
+    pub trait Fetch {
+        async fn fetch(&self) -> usize;
+
+        #[local]
+        async fn fetch_local(&self) -> usize;
+    }
+
+    #[async_trait(local, export_futures)]
+    pub trait Local {
+        async fn fetch(&self) -> usize;
+    }
+
+    pub fn test(object: &dyn Fetch) -> FetchFuture<'_, usize> {
+        object.fetch()
+    }
+
+    pub fn test_local(object: &dyn Local) -> LocalFuture<'_, usize> {
+        object.fetch()
+    }
+
+    #[async_trait(export_futures)]
+    pub trait Blocking {
+        fn fetch(&self) -> usize;
+    }
+
+    // Collides with the alias if one is emitted for a trait without async
+    // methods.
+    pub struct BlockingFuture;
+}
+
+pub async fn test_assoc_const() {
+    #[async_trait]
+    trait Trait {
+        const RETRIES: usize;
+
+        async fn retries(&self) -> usize {
+            Self::RETRIES + <Self as Trait>::RETRIES
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        const RETRIES: usize = 3;
+
+        async fn retries(&self) -> usize {
+            Self::RETRIES
+        }
+    }
+
+    Struct.retries().await;
+}
+
+pub async fn test_borrowed_buffer() {
+    struct Buffer<'a> {
+        data: &'a [u8],
+    }
+
+    #[async_trait]
+    trait Read {
+        async fn first(&self) -> Option<u8>;
+        async fn rest(&mut self) -> &[u8];
+        async fn into_data(self) -> usize;
+
+        async fn name(&self, _prefix: &str) -> &str {
+            "buffer"
+        }
+    }
+
+    #[async_trait]
+    impl<'a> Read for Buffer<'a> {
+        async fn first(&self) -> Option<u8> {
+            self.data.first().cloned()
+        }
+
+        async fn rest(&mut self) -> &[u8] {
+            if !self.data.is_empty() {
+                self.data = &self.data[1..];
+            }
+            self.data
+        }
+
+        async fn into_data(self) -> usize {
+            self.data.len()
+        }
+    }
+
+    let data = [1, 2, 3];
+    let mut buffer = Buffer { data: &data };
+    buffer.first().await;
+    buffer.rest().await;
+    buffer.name("").await;
+    buffer.into_data().await;
+}
+
+pub async fn test_higher_ranked_fn_output() {
+    fn identity(x: &u8) -> &u8 {
+        x
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn pointer(&self) -> fn(&u8) -> &u8;
+        async fn boxed(&self) -> Box<dyn Fn(&u8) -> &u8 + Send>;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn pointer(&self) -> fn(&u8) -> &u8 {
+            identity
+        }
+
+        async fn boxed(&self) -> Box<dyn Fn(&u8) -> &u8 + Send> {
+            Box::new(identity)
+        }
+    }
+
+    let _: for<'a> fn(&'a u8) -> &'a u8 = Struct.pointer().await;
+    let boxed = Struct.boxed().await;
+    let local = 0;
+    boxed(&local);
+}
+
+pub async fn test_boxed_error() {
+    use std::error::Error;
+
+    type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+    #[async_trait]
+    trait Trait {
+        async fn f(&self) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+        async fn g(&self, input: &str) -> Result<usize, BoxError> {
+            self.f().await?;
+            Ok(input.parse()?)
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn f(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+            Err("error".into())
+        }
+    }
+
+    let _ = Struct.g("0").await;
+}
+
+pub async fn test_blanket_impl_where_clause() {
+    trait Repository {
+        fn load(&self) -> u8;
+    }
+
+    #[async_trait]
+    trait Service {
+        async fn process(&self) -> u8;
+    }
+
+    #[async_trait]
+    impl<T> Service for T
+    where
+        T: Repository + Send + Sync,
+    {
+        async fn process(&self) -> u8 {
+            self.load()
+        }
+    }
+
+    impl Repository for Struct {
+        fn load(&self) -> u8 {
+            0
+        }
+    }
+
+    Struct.process().await;
+}
+
+pub async fn test_constructor() {
+    #[async_trait]
+    trait Trait: Sized {
+        async fn new() -> Self;
+
+        async fn new_from(name: &str) -> Self {
+            let _ = name;
+            Self::new().await
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn new() -> Self {
+            Struct
+        }
+    }
+
+    Struct::new().await;
+    Struct::new_from("").await;
+}
+
+pub async fn test_higher_ranked_bound() {
+    trait Lookup<'a> {
+        fn lookup(&self, key: &'a str) -> &'a str;
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn f(&self, key: &str) -> usize
+        where
+            for<'a> Self: Lookup<'a>,
+        {
+            self.lookup(key).len()
+        }
+    }
+
+    impl<'a> Lookup<'a> for Struct {
+        fn lookup(&self, key: &'a str) -> &'a str {
+            key
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {}
+
+    Struct.f("").await;
+}
+
+pub mod future_send_bound {
+    use async_trait::async_trait;
+    use std::marker::PhantomData;
+
+    pub trait SendLike {}
+
+    impl<T: Send> SendLike for T {}
+
+    #[async_trait(impl_future, future_send_bound = "T: SendLike")]
+    pub trait Trait {
+        async fn required(&self) -> usize;
+
+        async fn provided(&self) -> usize {
+            1
+        }
+    }
+
+    #[async_trait(future_send_bound = "T: Send + Sync")]
+    pub trait Object {
+        async fn f(&self);
+    }
+
+    pub struct Struct;
+
+    #[async_trait(impl_future, future_send_bound = "T: SendLike")]
+    impl Trait for Struct {
+        async fn required(&self) -> usize {
+            0
+        }
+    }
+
+    #[async_trait(future_send_bound = "T: Send + Sync")]
+    impl Object for Struct {
+        async fn f(&self) {}
+    }
+
+    pub async fn test(object: &dyn Object) {
+        fn assert_send_like<T: SendLike>(_: T) {}
+        assert_send_like(Struct.provided());
+        object.f().await;
+    }
+
+    // Sync but not Send, so the default methods must not require Send.
+    pub struct NotSend(PhantomData<*const ()>);
+
+    unsafe impl Sync for NotSend {}
+
+    #[async_trait(future_send_bound = "T: Sync")]
+    pub trait SyncOnly {
+        async fn by_ref(&self) {}
+
+        async fn by_mut(&mut self) {}
+
+        async fn by_value(self)
+        where
+            Self: Sized,
+        {
+        }
+    }
+
+    impl SyncOnly for NotSend {}
+
+    pub async fn test_sync_only(mut value: NotSend) {
+        fn assert_sync<T: Sync>(_: &T) {}
+        assert_sync(&value.by_ref());
+        value.by_ref().await;
+        value.by_mut().await;
+        value.by_value().await;
+    }
+}
+
+pub async fn test_implied_sync() {
+    #[async_trait]
+    trait Qualified: std::marker::Sync {
+        async fn qualified(&self) {}
+    }
+
+    #[async_trait]
+    trait WhereClause
+    where
+        Self: Sync,
+    {
+        async fn where_clause(&self) {}
+    }
+
+    #[async_trait]
+    impl Qualified for Struct {}
+
+    #[async_trait]
+    impl WhereClause for Struct {}
+
+    Struct.qualified().await;
+    Struct.where_clause().await;
+}
+
+pub async fn test_impl_for_dyn() {
+    trait Bar {
+        fn bar(&self) -> u8;
+    }
+
+    trait SyncBar: Send + Sync {
+        fn sync_bar(&self) -> u8;
+    }
+
+    #[async_trait]
+    trait Foo {
+        async fn foo(&self) -> u8;
+        async fn foo_mut(&mut self) -> u8;
+    }
+
+    #[async_trait]
+    impl Foo for dyn Bar + Send + Sync {
+        async fn foo(&self) -> u8 {
+            self.bar()
+        }
+
+        async fn foo_mut(&mut self) -> u8 {
+            Self::bar(self)
+        }
+    }
+
+    #[async_trait]
+    impl Foo for dyn SyncBar {
+        async fn foo(&self) -> u8 {
+            self.sync_bar()
+        }
+
+        async fn foo_mut(&mut self) -> u8 {
+            0
+        }
+    }
+
+    impl Bar for Struct {
+        fn bar(&self) -> u8 {
+            0
+        }
+    }
+
+    let object: &mut (dyn Bar + Send + Sync) = &mut Struct;
+    object.foo().await;
+    object.foo_mut().await;
+}
+
+pub async fn test_impl_for_fn_pointer() {
+    #[async_trait]
+    trait Apply {
+        async fn apply(&self, x: i32) -> i32;
+        async fn apply_once(self, x: i32) -> i32;
+    }
+
+    #[async_trait]
+    impl Apply for fn(i32) -> i32 {
+        async fn apply(&self, x: i32) -> i32 {
+            self(x)
+        }
+
+        async fn apply_once(self, x: i32) -> i32 {
+            let copy = self;
+            self(copy(x))
+        }
+    }
+
+    fn double(x: i32) -> i32 {
+        x * 2
+    }
+
+    let f: fn(i32) -> i32 = double;
+    f.apply(1).await;
+    f.apply_once(1).await;
+}
+
+pub async fn test_generic_associated_type() {
+    use std::future::{self, Future, Ready};
+
+    #[async_trait]
+    trait Trait {
+        type Item<'a>: Future<Output = &'a str> + Send
+        where
+            Self: 'a;
+
+        fn item(&self) -> Self::Item<'_>;
+
+        async fn len(&self) -> usize {
+            self.item().await.len()
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        type Item<'a> = Ready<&'a str>;
+
+        fn item(&self) -> Self::Item<'_> {
+            future::ready("")
+        }
+    }
+
+    Struct.len().await;
+}
+
+pub async fn test_interspersed_assoc_types() {
+    #[async_trait]
+    trait Trait {
+        type First;
+        async fn first(&self) -> Self::First;
+        type Second;
+        const THIRD: u8;
+        async fn second(&self) -> Self::Second;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        type First = String;
+        async fn first(&self) -> String {
+            String::new()
+        }
+        type Second = u8;
+        const THIRD: u8 = 3;
+        async fn second(&self) -> u8 {
+            Self::THIRD
+        }
+    }
+
+    Struct.first().await;
+    Struct.second().await;
+}
+
+pub async fn test_impl_for_reference() {
+    #[async_trait]
+    trait Trait {
+        async fn by_ref(&self) -> usize;
+        async fn by_value(self) -> usize;
+    }
+
+    #[async_trait]
+    impl Trait for &Vec<u8> {
+        async fn by_ref(&self) -> usize {
+            self.len()
+        }
+
+        async fn by_value(self) -> usize {
+            self.len()
+        }
+    }
+
+    #[async_trait]
+    impl Trait for &mut Vec<u16> {
+        async fn by_ref(&self) -> usize {
+            self.len()
+        }
+
+        async fn by_value(self) -> usize {
+            self.push(0);
+            self.len()
+        }
+    }
+
+    (&vec![0u8]).by_ref().await;
+    (&vec![0u8]).by_value().await;
+    (&mut vec![0u16]).by_ref().await;
+    (&mut vec![0u16]).by_value().await;
+}
+
+pub async fn test_future_returning_closures() {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    struct Data;
+
+    type Handler = dyn Fn(&Data) -> Pin<Box<dyn Future<Output = ()> + Send>> + Sync;
+
+    #[async_trait]
+    trait Trait {
+        async fn boxed(&self, f: &Handler) {
+            f(&Data).await;
+        }
+
+        async fn unboxed(&self, f: impl Fn() -> (impl Future<Output = ()> + Send) + Send) {
+            f().await;
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {}
+
+    let handler: &Handler = &|_| Box::pin(async {});
+    Struct.boxed(handler).await;
+    Struct.unboxed(|| async {}).await;
+}
+
+pub mod sealed {
+    use async_trait::async_trait;
+
+    mod private {
+        use async_trait::async_trait;
+
+        #[async_trait]
+        pub trait Sealed {
+            async fn private(&self) -> u8;
+        }
+    }
+
+    #[async_trait]
+    pub trait Public: private::Sealed + Sync {
+        async fn public(&self) -> u8 {
+            self.private().await
+        }
+    }
+
+    pub struct Struct;
+
+    #[async_trait]
+    impl private::Sealed for Struct {
+        async fn private(&self) -> u8 {
+            0
+        }
+    }
+
+    #[async_trait]
+    impl Public for Struct {}
+
+    pub async fn test() {
+        Struct.public().await;
+    }
+}
+
+pub mod anonymous_const {
+    use async_trait::async_trait;
+
+    #[async_trait]
+    pub trait Trait {
+        async fn f(&self) -> u8;
+    }
+
+    pub struct Struct;
+
+    const _: () = {
+        #[async_trait]
+        impl Trait for Struct {
+            async fn f(&self) -> u8 {
+                helper()
+            }
+        }
+
+        fn helper() -> u8 {
+            0
+        }
+    };
+
+    pub async fn test() {
+        Struct.f().await;
+    }
+}
+
+pub async fn test_assoc_type_output() {
+    #[async_trait]
+    trait Trait {
+        type Output: Default + Send;
+
+        async fn fetch(&self) -> Self::Output;
+
+        async fn fetch_or_default(&self, fetch: bool) -> Self::Output {
+            if fetch {
+                self.fetch().await
+            } else {
+                Self::Output::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        type Output = String;
+
+        async fn fetch(&self) -> Self::Output {
+            String::new()
+        }
+    }
+
+    Struct.fetch_or_default(true).await;
+}
+
+pub async fn test_static_self() {
+    use std::sync::Arc;
+
+    #[async_trait]
+    trait Supertrait: Send + Sync + 'static {
+        async fn f(&self) {}
+        async fn g(self: Arc<Self>) {}
+    }
+
+    #[async_trait]
+    trait WhereClause
+    where
+        Self: Send + Sync + 'static,
+    {
+        async fn f(&self) {}
+        async fn g(self: Arc<Self>) {}
+    }
+
+    #[async_trait]
+    impl Supertrait for Struct {
+        async fn f(&self) {}
+    }
+
+    #[async_trait]
+    impl WhereClause for Struct {
+        async fn g(self: Arc<Self>) {}
+    }
+
+    let object: Arc<dyn Supertrait> = Arc::new(Struct);
+    object.f().await;
+    object.g().await;
+    WhereClause::f(&Struct).await;
+    WhereClause::g(Arc::new(Struct)).await;
+}
+
+#[deny(warnings)]
+pub async fn test_repr_c() {
+    #[repr(C)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn sum(&self) -> i32;
+    }
+
+    #[async_trait]
+    impl Trait for Point {
+        #[inline]
+        async fn sum(&self) -> i32 {
+            self.x + self.y
+        }
+    }
+
+    Point { x: 0, y: 0 }.sum().await;
+}
+
+pub async fn test_stream_return() {
+    use std::pin::Pin;
+
+    // Stand-in for futures::Stream.
+    trait Stream {
+        type Item;
+    }
+
+    struct Empty;
+
+    impl Stream for Empty {
+        type Item = u8;
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn boxed(&self) -> Pin<Box<dyn Stream<Item = u8> + Send>>;
+        async fn opaque(&self) -> impl Stream<Item = u8>;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn boxed(&self) -> Pin<Box<dyn Stream<Item = u8> + Send>> {
+            Box::pin(Empty)
+        }
+
+        async fn opaque(&self) -> impl Stream<Item = u8> {
+            Empty
+        }
+    }
+
+    Struct.boxed().await;
+    Struct.opaque().await;
+}
+
+pub mod trait_must_use {
+    use async_trait::async_trait;
+
+    #[async_trait]
+    #[must_use = "the returned futures do nothing unless awaited"]
+    pub trait Trait {
+        async fn f(&self);
+
+        #[must_use]
+        async fn g(&self) -> u8;
+    }
+
+    pub struct Struct;
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn f(&self) {}
+
+        async fn g(&self) -> u8 {
+            0
+        }
+    }
+}
+
+pub async fn test_sized_default_overridden() {
+    #[async_trait]
+    trait Trait {
+        async fn sized(&self) -> u8
+        where
+            Self: Sized,
+        {
+            0
+        }
+
+        async fn dyn_dispatch(&self);
+    }
+
+    struct Restated;
+
+    #[async_trait]
+    impl Trait for Restated {
+        async fn sized(&self) -> u8
+        where
+            Self: Sized,
+        {
+            1
+        }
+
+        async fn dyn_dispatch(&self) {}
+    }
+
+    struct Unbounded;
+
+    #[async_trait]
+    impl Trait for Unbounded {
+        async fn sized(&self) -> u8 {
+            2
+        }
+
+        async fn dyn_dispatch(&self) {}
+    }
+
+    Restated.sized().await;
+    Unbounded.sized().await;
+
+    let object: &dyn Trait = &Unbounded;
+    object.dyn_dispatch().await;
+}
+
+pub mod indirect_sync_supertrait {
+    use async_trait::async_trait;
+
+    pub trait Bar: Sync {}
+
+    #[async_trait]
+    pub trait Foo: Bar {
+        async fn f(&self) {}
+    }
+
+    pub struct Struct;
+
+    impl Bar for Struct {}
+
+    #[async_trait]
+    impl Foo for Struct {}
+}
+
+pub mod lint_trivial_async {
+    use async_trait::async_trait;
+
+    #[async_trait(lint_trivial_async)]
+    pub trait Trait {
+        async fn required(&self) -> u8;
+
+        async fn awaits(&self) -> u8 {
+            self.required().await
+        }
+    }
+}
+
+pub async fn test_rc_self_local() {
+    use std::rc::Rc;
+
+    #[async_trait(local)]
+    trait Trait {
+        async fn required(self: Rc<Self>) -> u8;
+
+        async fn provided(self: Rc<Self>) -> u8 {
+            self.required().await
+        }
+    }
+
+    struct Local;
+
+    #[async_trait(local)]
+    impl Trait for Local {
+        async fn required(self: Rc<Self>) -> u8 {
+            let _: Rc<Local> = self;
+            0
+        }
+    }
+
+    Rc::new(Local).provided().await;
+}
+
+pub async fn test_static_method_not_sync() {
+    use std::cell::Cell;
+
+    #[async_trait]
+    trait Trait {
+        async fn provided(x: u8) -> u8 {
+            x
+        }
+
+        async fn required(x: &str) -> usize;
+    }
+
+    struct NotSync(Cell<u8>);
+
+    #[async_trait]
+    impl Trait for NotSync {
+        async fn required(x: &str) -> usize {
+            x.len()
+        }
+    }
+
+    let _ = NotSync(Cell::new(0)).0;
+    NotSync::provided(0).await;
+    NotSync::required("").await;
+}
+
+pub async fn test_impl_async_read_arg() {
+    // Stand-in for tokio::io::AsyncRead.
+    trait AsyncRead {}
+
+    impl AsyncRead for &[u8] {}
+
+    #[async_trait]
+    trait Trait {
+        async fn read(&self, reader: impl AsyncRead + Unpin + Send) -> usize;
+
+        async fn provided(&self, mut reader: impl AsyncRead + Unpin + Send + 'static) {
+            let _ = &mut reader;
+            self.read(reader).await;
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn read(&self, _reader: impl AsyncRead + Unpin + Send) -> usize {
+            0
+        }
+    }
+
+    let bytes: &'static [u8] = b"";
+    Struct.read(bytes).await;
+    Struct.provided(bytes).await;
+}
+
+pub async fn test_explicit_boxed_future() {
+    use std::future::Future;
+    use std::pin::Pin;
+
+    #[async_trait]
+    trait Trait {
+        fn expanded<'a>(&'a self) -> Pin<Box<dyn Future<Output = u8> + Send + 'a>>;
+
+        async fn transformed(&self) -> u8;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        fn expanded<'a>(&'a self) -> Pin<Box<dyn Future<Output = u8> + Send + 'a>> {
+            Box::pin(async { 0 })
+        }
+
+        async fn transformed(&self) -> u8 {
+            self.expanded().await
+        }
+    }
+
+    Struct.expanded().await;
+    Struct.transformed().await;
+}
+
+#[no_implicit_prelude]
+pub mod no_implicit_prelude {
+    #[::async_trait::async_trait]
+    pub trait Trait {
+        async fn required(&self, x: &str) -> u8;
+
+        async fn provided(&self) -> u8 {
+            self.required("").await
+        }
+
+        async fn by_value(self) -> u8
+        where
+            Self: ::std::marker::Sized,
+        {
+            0
+        }
+    }
+
+    pub struct Struct;
+
+    #[::async_trait::async_trait]
+    impl Trait for Struct {
+        async fn required(&self, _x: &str) -> u8 {
+            0
+        }
+    }
+}
+
+pub async fn test_split_field_borrows() {
+    #[async_trait]
+    trait Trait {
+        async fn process(&mut self);
+    }
+
+    struct Fields {
+        input: Vec<u8>,
+        output: Vec<u8>,
+    }
+
+    async fn step() {}
+
+    #[async_trait]
+    impl Trait for Fields {
+        async fn process(&mut self) {
+            let input = &self.input;
+            let output = &mut self.output;
+            for byte in input {
+                step().await;
+                output.push(*byte);
+            }
+        }
+    }
+
+    let mut fields = Fields {
+        input: Vec::new(),
+        output: Vec::new(),
+    };
+    fields.process().await;
+}
+
+pub async fn test_impl_future_in_bounds() {
+    use std::future::Future;
+
+    #[async_trait]
+    trait Trait: Sync {
+        async fn with_self<F, R>(&self, f: F) -> R
+        where
+            F: Fn(&Self) -> (impl Future<Output = R> + Send) + Send,
+            R: Send;
+
+        // The macro adds `F: 'async_trait` to the where clause.
+        #[allow(clippy::multiple_bound_locations)]
+        async fn inline<F: Fn() -> (impl Future<Output = u8> + Send) + Send + Sync>(
+            &self,
+            f: F,
+        ) -> u8 {
+            f().await
+        }
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn with_self<F, R>(&self, f: F) -> R
+        where
+            F: Fn(&Self) -> (impl Future<Output = R> + Send) + Send,
+            R: Send,
+        {
+            f(self).await
+        }
+    }
+
+    Struct.with_self(|_| async { 0u8 }).await;
+    Struct.inline(|| async { 0 }).await;
+}
+
+pub async fn test_unsized_self() {
+    #[async_trait]
+    trait Trait {
+        async fn count(&self) -> usize;
+
+        async fn twice(&self) -> usize {
+            self.count().await * 2
+        }
+
+        async fn boxed(self: Box<Self>) -> usize {
+            self.count().await
+        }
+    }
+
+    #[async_trait]
+    impl<T: Send + Sync> Trait for [T] {
+        async fn count(&self) -> usize {
+            self.len()
+        }
+    }
+
+    #[async_trait]
+    impl Trait for str {
+        async fn count(&self) -> usize {
+            self.len()
+        }
+    }
+
+    let slice: &[u8] = &[0];
+    slice.count().await;
+    slice.twice().await;
+    "".twice().await;
+    let boxed: Box<[u8]> = Box::new([0]);
+    boxed.boxed().await;
+}
+
+pub async fn test_question_mark_custom_error() {
+    use std::io;
+
+    struct MyError;
+
+    impl From<io::Error> for MyError {
+        fn from(_error: io::Error) -> Self {
+            MyError
+        }
+    }
+
+    async fn read() -> io::Result<u8> {
+        Ok(0)
+    }
+
+    #[async_trait]
+    trait Trait {
+        async fn provided(&self) -> Result<u8, MyError> {
+            let byte = read().await?;
+            Ok(byte)
+        }
+
+        async fn required(&self) -> Result<(), MyError>;
+    }
+
+    #[async_trait]
+    impl Trait for Struct {
+        async fn required(&self) -> Result<(), MyError> {
+            read().await?;
+            Ok(())
+        }
+    }
+
+    let _ = Struct.provided().await;
+    let _ = Struct.required().await;
+}
+
+// Crates using compat declare the native-async-trait feature themselves.
+#[allow(unexpected_cfgs)]
+pub mod compat {
+    use async_trait::async_trait;
+
+    #[async_trait(compat)]
+    pub trait Trait {
+        async fn required(&self) -> u8;
+
+        #[local]
+        async fn provided(&self) -> u8 {
+            self.required().await
+        }
+    }
+
+    pub struct Struct;
+
+    #[async_trait(compat)]
+    impl Trait for Struct {
+        async fn required(&self) -> u8 {
+            0
+        }
+    }
+}
+
+pub async fn test_send_not_sync_self() {
+    use std::cell::Cell;
+
+    #[async_trait]
+    trait Trait {
+        async fn by_mut(&mut self) -> u8;
+
+        async fn by_value(self) -> u8
+        where
+            Self: Sized,
+        {
+            0
+        }
+    }
+
+    struct SendNotSync(Cell<u8>);
+
+    #[async_trait]
+    impl Trait for SendNotSync {
+        async fn by_mut(&mut self) -> u8 {
+            self.0.get()
+        }
+    }
+
+    let mut value = SendNotSync(Cell::new(0));
+    value.by_mut().await;
+    value.by_value().await;
+}
+
+pub async fn test_raw_pointer_field() {
+    #[async_trait]
+    trait Trait {
+        async fn f(&self) -> bool;
+    }
+
+    struct RawPointer(*const u8);
+
+    // Never dereferenced.
+    unsafe impl Send for RawPointer {}
+    unsafe impl Sync for RawPointer {}
+
+    #[async_trait]
+    impl Trait for RawPointer {
+        async fn f(&self) -> bool {
+            self.0.is_null()
+        }
+    }
+
+    RawPointer(std::ptr::null()).f().await;
+}
+
+pub async fn test_default_calls_supertrait() {
+    #[async_trait]
+    trait Super: Sync {
+        async fn base(&self) -> u8;
+
+        async fn base_mut(&mut self) -> u8;
+
+        async fn base_static() -> u8;
+    }
+
+    #[async_trait]
+    trait Sub: Super {
+        async fn derived(&self) -> u8 {
+            self.base().await + Self::base_static().await
+        }
+
+        async fn derived_mut(&mut self) -> u8 {
+            self.base_mut().await + self.base().await
+        }
+
+        async fn derived_static() -> u8 {
+            <Self as Super>::base_static().await
+        }
+    }
+
+    #[async_trait]
+    impl Super for Struct {
+        async fn base(&self) -> u8 {
+            0
+        }
+
+        async fn base_mut(&mut self) -> u8 {
+            0
+        }
+
+        async fn base_static() -> u8 {
+            0
+        }
+    }
+
+    impl Sub for Struct {}
+
+    Struct.derived().await;
+    Struct.derived_mut().await;
+    Struct::derived_static().await;
+}
+
+pub async fn test_query_database_supertrait() {
+    use std::collections::HashMap;
+
+    // Stand-in for a trait generated by #[salsa::query_group].
+    trait Source {
+        fn text(&self, path: &str) -> Option<String>;
+        fn set_text(&mut self, path: String, text: String);
+    }
+
+    async fn download(path: &str) -> String {
+        path.to_owned()
+    }
+
+    #[async_trait]
+    trait Fetch: Source {
+        async fn refresh(&mut self, path: String) -> Option<String> {
+            let text = download(&path).await;
+            self.set_text(path.clone(), text);
+            self.text(&path)
+        }
+    }
+
+    struct Database(HashMap<String, String>);
+
+    impl Source for Database {
+        fn text(&self, path: &str) -> Option<String> {
+            self.0.get(path).cloned()
+        }
+
+        fn set_text(&mut self, path: String, text: String) {
+            self.0.insert(path, text);
+        }
+    }
+
+    impl Fetch for Database {}
+
+    Database(HashMap::new()).refresh(String::new()).await;
+}
+
 // https://github.com/dtolnay/async-trait/issues/1
 mod issue1 {
     use async_trait::async_trait;